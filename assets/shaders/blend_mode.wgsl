@@ -0,0 +1,17 @@
+// Fragment shader for the processing_like2 shape meshes. The blend state of a
+// `BlendMode` can't weigh every mode by the source alpha on its own, so the
+// vertex color is first faded towards the mode's identity here.
+
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = in.color;
+#ifdef FADE_TO_WHITE
+    // MULTIPLY and DARKEST, where white leaves the destination unchanged.
+    return vec4<f32>(mix(vec3<f32>(1.0), color.rgb, color.a), color.a);
+#else
+    // The others, where black does: premultiplied alpha.
+    return vec4<f32>(color.rgb * color.a, color.a);
+#endif
+}
@@ -0,0 +1,159 @@
+//! `blend_mode()`: how shapes combine with what is already on the canvas. Each
+//! run of shapes drawn in one mode is batched into its own mesh, drawn with the
+//! `BlendMaterial` whose pipeline sets that mode's GPU blend state.
+
+use bevy::prelude::*;
+use bevy::render::mesh::MeshVertexBufferLayoutRef;
+use bevy::render::render_resource::{
+    AsBindGroup, BlendComponent, BlendFactor, BlendOperation, BlendState, RenderPipelineDescriptor,
+    ShaderRef, SpecializedMeshPipelineError,
+};
+use bevy::sprite::{AlphaMode2d, Material2d, Material2dKey, Material2dPlugin};
+
+use super::with_state;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum BlendMode {
+    /// Alpha blending.
+    #[default]
+    Blend,
+    /// Adds to the colors below, so overlapping shapes glow towards white.
+    Add,
+    /// Subtracts from the colors below.
+    Subtract,
+    /// Keeps the lighter value of each channel.
+    Lightest,
+    /// Keeps the darker value of each channel.
+    Darkest,
+    /// Multiplies with the colors below, always darkening.
+    Multiply,
+    /// Multiplies the inverses, always lightening.
+    Screen,
+}
+
+impl BlendMode {
+    const ALL: [BlendMode; 7] = [
+        BlendMode::Blend,
+        BlendMode::Add,
+        BlendMode::Subtract,
+        BlendMode::Lightest,
+        BlendMode::Darkest,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+    ];
+
+    /// Blends `src` onto `dst` the way the GPU does with this mode's material,
+    /// for the CPU renderer. Each mode is weighed by the source alpha.
+    pub(super) fn apply(self, src: LinearRgba, dst: LinearRgba) -> LinearRgba {
+        let a = src.alpha;
+        let s = src.to_vec4().truncate();
+        let d = dst.to_vec4().truncate();
+        // What blend_mode.wgsl outputs for each mode.
+        let premultiplied = s * a;
+        let faded = Vec3::ONE.lerp(s, a);
+        let rgb = match self {
+            BlendMode::Blend => premultiplied + d * (1.0 - a),
+            BlendMode::Add => (d + premultiplied).min(Vec3::ONE),
+            BlendMode::Subtract => (d - premultiplied).max(Vec3::ZERO),
+            BlendMode::Lightest => d.max(premultiplied),
+            BlendMode::Darkest => d.min(faded),
+            BlendMode::Multiply => d * faded,
+            BlendMode::Screen => premultiplied + d * (Vec3::ONE - premultiplied),
+        };
+        LinearRgba::new(rgb.x, rgb.y, rgb.z, a + dst.alpha * (1.0 - a))
+    }
+
+    fn blend_state(self) -> BlendState {
+        use BlendFactor::{Dst, One, OneMinusSrc, OneMinusSrcAlpha, Zero};
+        let (src_factor, dst_factor, operation) = match self {
+            BlendMode::Blend => (One, OneMinusSrcAlpha, BlendOperation::Add),
+            BlendMode::Add => (One, One, BlendOperation::Add),
+            BlendMode::Subtract => (One, One, BlendOperation::ReverseSubtract),
+            BlendMode::Lightest => (One, One, BlendOperation::Max),
+            BlendMode::Darkest => (One, One, BlendOperation::Min),
+            BlendMode::Multiply => (Dst, Zero, BlendOperation::Add),
+            BlendMode::Screen => (One, OneMinusSrc, BlendOperation::Add),
+        };
+        BlendState {
+            color: BlendComponent {
+                src_factor,
+                dst_factor,
+                operation,
+            },
+            alpha: BlendComponent::OVER,
+        }
+    }
+}
+
+/// Sets how the shapes drawn from now on combine with what is below them.
+/// Text and images always use `BlendMode::Blend`.
+pub fn blend_mode(mode: BlendMode) {
+    with_state(|s| s.style.blend = mode);
+}
+
+/// The material of a shape mesh, with vertex colors and the blend state of `mode`.
+#[derive(Asset, TypePath, AsBindGroup, Clone)]
+#[bind_group_data(BlendMode)]
+pub(super) struct BlendMaterial {
+    mode: BlendMode,
+}
+
+impl From<&BlendMaterial> for BlendMode {
+    fn from(material: &BlendMaterial) -> Self {
+        material.mode
+    }
+}
+
+impl Material2d for BlendMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/blend_mode.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode2d {
+        // Sorted back to front with the images and text.
+        AlphaMode2d::Blend
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayoutRef,
+        key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let mode = key.bind_group_data;
+        if let Some(fragment) = &mut descriptor.fragment {
+            if matches!(mode, BlendMode::Darkest | BlendMode::Multiply) {
+                fragment.shader_defs.push("FADE_TO_WHITE".into());
+            }
+            for target in fragment.targets.iter_mut().flatten() {
+                target.blend = Some(mode.blend_state());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A material for every mode, shared by all canvases.
+#[derive(Resource)]
+pub(super) struct BlendMaterials([Handle<BlendMaterial>; 7]);
+
+impl BlendMaterials {
+    pub(super) fn get(&self, mode: BlendMode) -> Handle<BlendMaterial> {
+        self.0[mode as usize].clone()
+    }
+}
+
+impl FromWorld for BlendMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<BlendMaterial>>();
+        Self(BlendMode::ALL.map(|mode| materials.add(BlendMaterial { mode })))
+    }
+}
+
+pub(super) struct BlendPlugin;
+
+impl Plugin for BlendPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(Material2dPlugin::<BlendMaterial>::default())
+            .init_resource::<BlendMaterials>();
+    }
+}
@@ -0,0 +1,138 @@
+//! Processing's `color()` family: numbers are read in the current `color_mode()`
+//! and its ranges, and turned into Bevy `Color`s in sRGB.
+
+use bevy::color::{Hsva, Srgba};
+use bevy::prelude::*;
+
+use super::with_state;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ColorMode {
+    #[default]
+    Rgb,
+    /// Hue, saturation and brightness (HSV).
+    Hsb,
+}
+
+/// The current mode together with the maximum of each of its channels.
+#[derive(Clone, Copy)]
+pub(super) struct ColorSettings {
+    mode: ColorMode,
+    max: [f32; 4],
+}
+
+impl Default for ColorSettings {
+    fn default() -> Self {
+        Self {
+            mode: ColorMode::Rgb,
+            max: [255.0; 4],
+        }
+    }
+}
+
+impl ColorSettings {
+    fn to_color(self, v: [f32; 4]) -> Color {
+        let [a, b, c, alpha] = std::array::from_fn(|i| (v[i] / self.max[i]).clamp(0.0, 1.0));
+        match self.mode {
+            ColorMode::Rgb => Color::srgba(a, b, c, alpha),
+            ColorMode::Hsb => Color::hsva(a * 360.0, b, c, alpha),
+        }
+    }
+}
+
+fn settings() -> ColorSettings {
+    with_state(|s| s.color)
+}
+
+/// Switches how `color()` reads its numbers, keeping the current ranges.
+pub fn color_mode(mode: ColorMode) {
+    with_state(|s| s.color.mode = mode);
+}
+/// Switches mode and sets the value that means "full" for each channel, e.g.
+/// `color_mode_max(ColorMode::Hsb, 360.0, 100.0, 100.0, 1.0)`.
+pub fn color_mode_max(mode: ColorMode, max1: f32, max2: f32, max3: f32, max_alpha: f32) {
+    with_state(|s| {
+        s.color = ColorSettings {
+            mode,
+            max: [max1, max2, max3, max_alpha],
+        }
+    });
+}
+
+/// Like `color_mode_max()`, but keeps the current alpha range.
+pub fn color_mode_ranges(mode: ColorMode, max1: f32, max2: f32, max3: f32) {
+    with_state(|s| {
+        s.color.mode = mode;
+        s.color.max[..3].copy_from_slice(&[max1, max2, max3]);
+    });
+}
+
+pub fn color(v1: f32, v2: f32, v3: f32) -> Color {
+    let settings = settings();
+    settings.to_color([v1, v2, v3, settings.max[3]])
+}
+pub fn color_alpha(v1: f32, v2: f32, v3: f32, alpha: f32) -> Color {
+    settings().to_color([v1, v2, v3, alpha])
+}
+/// A gray in the range of the first channel, whatever the mode.
+pub fn gray(v: f32) -> Color {
+    gray_alpha(v, settings().max[3])
+}
+pub fn gray_alpha(v: f32, alpha: f32) -> Color {
+    let max = settings().max;
+    let v = (v / max[0]).clamp(0.0, 1.0);
+    Color::srgba(v, v, v, (alpha / max[3]).clamp(0.0, 1.0))
+}
+
+/// Parses `#RRGGBB` or `#RRGGBBAA`; the `#` is optional.
+pub fn hex_color(hex: &str) -> Option<Color> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    if !matches!(digits.len(), 6 | 8) || !digits.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+    let alpha = if digits.len() == 8 { channel(6)? } else { 255 };
+    Some(Color::srgba_u8(
+        channel(0)?,
+        channel(2)?,
+        channel(4)?,
+        alpha,
+    ))
+}
+
+/// Blends from `from` to `to` by `amount` in 0..=1. In `ColorMode::Hsb` the hue
+/// takes the shorter way round the color wheel.
+pub fn lerp_color(from: Color, to: Color, amount: f32) -> Color {
+    let t = amount.clamp(0.0, 1.0);
+    match settings().mode {
+        ColorMode::Rgb => Srgba::from(from).mix(&Srgba::from(to), t).into(),
+        ColorMode::Hsb => Hsva::from(from).mix(&Hsva::from(to), t).into(),
+    }
+}
+
+/// Scales a 0..=1 channel value to the current range of channel `index`.
+fn scaled(index: usize, value: f32) -> f32 {
+    value * settings().max[index]
+}
+
+pub fn red(c: Color) -> f32 {
+    scaled(0, Srgba::from(c).red)
+}
+pub fn green(c: Color) -> f32 {
+    scaled(1, Srgba::from(c).green)
+}
+pub fn blue(c: Color) -> f32 {
+    scaled(2, Srgba::from(c).blue)
+}
+pub fn alpha(c: Color) -> f32 {
+    scaled(3, c.alpha())
+}
+pub fn hue(c: Color) -> f32 {
+    scaled(0, Hsva::from(c).hue / 360.0)
+}
+pub fn saturation(c: Color) -> f32 {
+    scaled(1, Hsva::from(c).saturation)
+}
+pub fn brightness(c: Color) -> f32 {
+    scaled(2, Hsva::from(c).value)
+}
@@ -0,0 +1,85 @@
+//! Flattening of Bézier, Catmull-Rom and elliptical curves into polylines.
+//!
+//! Every function takes a `tolerance`: the largest distance, in the same units
+//! as the points, that the polyline may stray from the true curve.
+
+use bevy::math::Vec2;
+
+const MAX_DEPTH: u32 = 16;
+
+/// Appends points along the cubic `p0 c1 c2 p3` to `out`, excluding `p0`.
+pub fn flatten_cubic(out: &mut Vec<Vec2>, p0: Vec2, c1: Vec2, c2: Vec2, p3: Vec2, tolerance: f32) {
+    subdivide_cubic(out, [p0, c1, c2, p3], tolerance.max(1e-4), 0);
+}
+
+fn subdivide_cubic(out: &mut Vec<Vec2>, [p0, c1, c2, p3]: [Vec2; 4], tolerance: f32, depth: u32) {
+    if depth >= MAX_DEPTH || cubic_is_flat(p0, c1, c2, p3, tolerance) {
+        out.push(p3);
+        return;
+    }
+    // de Casteljau split at t = 0.5.
+    let p01 = p0.lerp(c1, 0.5);
+    let p12 = c1.lerp(c2, 0.5);
+    let p23 = c2.lerp(p3, 0.5);
+    let p012 = p01.lerp(p12, 0.5);
+    let p123 = p12.lerp(p23, 0.5);
+    let mid = p012.lerp(p123, 0.5);
+    subdivide_cubic(out, [p0, p01, p012, mid], tolerance, depth + 1);
+    subdivide_cubic(out, [mid, p123, p23, p3], tolerance, depth + 1);
+}
+
+/// A cubic lies within `tolerance` of its chord if both control points do,
+/// since the curve stays inside the hull of its control polygon.
+fn cubic_is_flat(p0: Vec2, c1: Vec2, c2: Vec2, p3: Vec2, tolerance: f32) -> bool {
+    let chord = p3 - p0;
+    let len = chord.length();
+    let distance = |p: Vec2| {
+        if len < 1e-6 {
+            p.distance(p0)
+        } else {
+            chord.perp_dot(p - p0).abs() / len
+        }
+    };
+    distance(c1).max(distance(c2)) <= tolerance
+}
+
+/// Appends points along the quadratic `p0 c p2` to `out`, excluding `p0`.
+pub fn flatten_quadratic(out: &mut Vec<Vec2>, p0: Vec2, c: Vec2, p2: Vec2, tolerance: f32) {
+    // Degree elevation: the same curve expressed as a cubic.
+    let c1 = p0 + (c - p0) * (2.0 / 3.0);
+    let c2 = p2 + (c - p2) * (2.0 / 3.0);
+    flatten_cubic(out, p0, c1, c2, p2, tolerance);
+}
+
+/// Appends the Catmull-Rom segment from `p1` to `p2` to `out`, excluding `p1`;
+/// `p0` and `p3` only steer the tangents.
+pub fn flatten_catmull_rom(out: &mut Vec<Vec2>, [p0, p1, p2, p3]: [Vec2; 4], tolerance: f32) {
+    let c1 = p1 + (p2 - p0) / 6.0;
+    let c2 = p2 - (p3 - p1) / 6.0;
+    flatten_cubic(out, p1, c1, c2, p2, tolerance);
+}
+
+/// Points along an elliptical arc from `start` to `stop` radians, both ends included.
+pub fn elliptical_arc(
+    center: Vec2,
+    radii: Vec2,
+    start: f32,
+    stop: f32,
+    tolerance: f32,
+) -> Vec<Vec2> {
+    let sweep = stop - start;
+    let radius = radii.x.abs().max(radii.y.abs());
+    // A chord spanning `step` radians bulges r * (1 - cos(step / 2)) away from the arc.
+    let step = if radius > tolerance {
+        2.0 * (1.0 - tolerance / radius).acos()
+    } else {
+        std::f32::consts::FRAC_PI_2
+    };
+    let segments = ((sweep.abs() / step).ceil() as usize).clamp(1, 1024);
+    (0..=segments)
+        .map(|i| {
+            let a = start + sweep * i as f32 / segments as f32;
+            center + Vec2::new(a.cos(), a.sin()) * radii
+        })
+        .collect()
+}
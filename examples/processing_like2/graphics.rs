@@ -0,0 +1,168 @@
+//! `create_graphics()`: offscreen layers with the whole drawing API, shown with
+//! `image()`. Each layer is a canvas on its own render layer, drawn by its own
+//! camera into an `Image` that keeps what was drawn on it from frame to frame.
+
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+use bevy::math::Affine2;
+use bevy::prelude::*;
+use bevy::render::camera::{CameraOutputMode, RenderTarget};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{
+    BlendState, Extent3d, TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::render::view::RenderLayers;
+
+use super::image::PImage;
+use super::svg::record_frame;
+use super::{Canvas, SketchState, Surface, run_user, with_state};
+
+/// An offscreen layer returned by `create_graphics()`. Draw on it with `draw()`
+/// and show it with `image()` like any `PImage`. What is drawn on a layer stays
+/// there until drawn over, so a translucent `background()` leaves trails.
+#[derive(Clone)]
+pub struct PGraphics {
+    image: PImage,
+    /// The layer's own style, matrix and commands, shared with its `GraphicsLayer`.
+    state: Arc<Mutex<SketchState>>,
+}
+
+impl Deref for PGraphics {
+    type Target = PImage;
+
+    fn deref(&self) -> &PImage {
+        &self.image
+    }
+}
+
+impl PGraphics {
+    /// Runs `f` with this layer as the target of every sketch function it
+    /// calls, like the code between Processing's `beginDraw()` and `endDraw()`.
+    /// The matrix starts from the identity; fill, stroke and the rest carry
+    /// over from the last call.
+    pub fn draw<R>(&self, f: impl FnOnce() -> R) -> R {
+        let outer = with_state(|s| {
+            (
+                s.asset_server.clone(),
+                s.font_data.clone(),
+                s.images.clone(),
+                s.input.clone(),
+                s.frame_count,
+                s.millis,
+            )
+        });
+        let mut state = self.state.lock().unwrap();
+        // Loaded assets, input and time are the sketch's, not the layer's.
+        (
+            state.asset_server,
+            state.font_data,
+            state.images,
+            state.input,
+            state.frame_count,
+            state.millis,
+        ) = outer;
+        state.matrix = Affine2::IDENTITY;
+        state.matrix_stack.clear();
+        run_user(&mut state, f)
+    }
+}
+
+/// Creates a transparent `w` by `h` pixel layer.
+pub fn create_graphics(w: f32, h: f32) -> PGraphics {
+    let size = Vec2::new(w, h).max(Vec2::ONE);
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: size.x as u32,
+            height: size.y as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0; 4],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage |= TextureUsages::RENDER_ATTACHMENT;
+    with_state(|s| {
+        let handle = s
+            .asset_server
+            .as_ref()
+            .map(|server| server.add(image.clone()))
+            .unwrap_or_default();
+        // So that its size is known before it has loaded.
+        s.images.insert(handle.id(), Arc::new(image));
+        let graphics = PGraphics {
+            image: PImage(handle),
+            state: Arc::new(Mutex::new(SketchState {
+                surface: Surface { size, ..default() },
+                ..default()
+            })),
+        };
+        s.graphics.push(graphics.clone());
+        graphics
+    })
+}
+
+/// The canvas of a `PGraphics`, rendered by a `LayerCamera` on the same render layer.
+#[derive(Component)]
+#[require(Canvas)]
+pub(super) struct GraphicsLayer(PGraphics);
+
+/// Draws a `GraphicsLayer` into its image. Not the camera the window shows.
+#[derive(Component)]
+pub(super) struct LayerCamera;
+
+/// Spawns a canvas and camera for every layer created since the last frame.
+pub(super) fn spawn_graphics(
+    mut commands: Commands,
+    mut canvases: Query<&mut SketchState>,
+    mut layer_count: Local<usize>,
+) {
+    for mut state in &mut canvases {
+        for graphics in state.graphics.drain(..) {
+            // Layer 0 is everything else's.
+            *layer_count += 1;
+            let layers = RenderLayers::layer(*layer_count);
+            commands.spawn((
+                Camera2d,
+                Camera {
+                    target: RenderTarget::Image(graphics.image.0.clone().into()),
+                    // Before the cameras that show the layer.
+                    order: -1,
+                    clear_color: ClearColorConfig::Custom(Color::NONE),
+                    // Composite each frame onto the image instead of replacing it.
+                    output_mode: CameraOutputMode::Write {
+                        blend_state: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                        clear_color: ClearColorConfig::None,
+                    },
+                    ..default()
+                },
+                LayerCamera,
+                layers.clone(),
+            ));
+            commands.spawn((GraphicsLayer(graphics), layers));
+        }
+    }
+}
+
+/// Hands what was drawn on each layer this frame to its canvas for rendering.
+pub(super) fn sync_graphics(mut layers: Query<(&GraphicsLayer, &mut SketchState)>) {
+    for (layer, mut state) in &mut layers {
+        let mut shared = layer.0.state.lock().unwrap();
+        record_frame(&mut shared);
+        state.commands.append(&mut shared.commands);
+        // Layers created while drawing on this one.
+        state.graphics.append(&mut shared.graphics);
+        state.surface = shared.surface;
+    }
+}
+
+/// The image a layer draws into.
+pub(super) fn layer_image(layer: &GraphicsLayer) -> &Handle<Image> {
+    &layer.0.image.0
+}
+
+/// The state a layer's `draw()` calls write to.
+pub(super) fn layer_state(layer: &GraphicsLayer) -> &Mutex<SketchState> {
+    &layer.0.state
+}
@@ -0,0 +1,386 @@
+//! `load_image()`/`image()`. Every drawn image gets a pooled entity with its
+//! own quad mesh and material, rewritten only when what it shows changes.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bevy::math::Affine2;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::view::{NoFrustumCulling, RenderLayers};
+
+use super::{ProcessingCommand, SketchState, matrix, send, to_world, with_state};
+
+/// An image returned by `load_image()`.
+#[derive(Clone)]
+pub struct PImage(pub(super) Handle<Image>);
+
+impl PImage {
+    /// Width in pixels, or 0 while the image is still loading.
+    pub fn width(&self) -> f32 {
+        self.size().x
+    }
+    /// Height in pixels, or 0 while the image is still loading.
+    pub fn height(&self) -> f32 {
+        self.size().y
+    }
+    fn size(&self) -> Vec2 {
+        with_state(|s| s.images.get(&self.0.id()).map(|image| image.size_f32()))
+            .unwrap_or(Vec2::ZERO)
+    }
+}
+
+/// How the four numbers passed to `image()` are read, as in Processing.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ImageMode {
+    /// Top-left corner, then width and height.
+    #[default]
+    Corner,
+    /// Two opposite corners.
+    Corners,
+    /// Centre, then width and height.
+    Center,
+}
+
+/// Loads an image from `assets/`. Until it has loaded it draws nothing.
+pub fn load_image(path: &str) -> PImage {
+    with_state(|s| match &s.asset_server {
+        Some(assets) => PImage(assets.load(path.to_owned())),
+        None => {
+            warn!("load_image() called outside a running sketch");
+            PImage(Handle::default())
+        }
+    })
+}
+pub fn image_mode(mode: ImageMode) {
+    with_state(|s| s.image_mode = mode);
+}
+/// Multiplies every image drawn afterwards by `color`; use alpha to fade them.
+pub fn tint(color: Color) {
+    with_state(|s| s.tint = Some(color));
+}
+pub fn no_tint() {
+    with_state(|s| s.tint = None);
+}
+/// Draws the whole image at its natural size.
+pub fn image(img: &PImage, x: f32, y: f32) {
+    send_image(img, Vec2::new(x, y), None, None);
+}
+/// Draws the whole image stretched to `w` by `h` (or to the corner `(w, h)` in
+/// `ImageMode::Corners`).
+pub fn image_sized(img: &PImage, x: f32, y: f32, w: f32, h: f32) {
+    send_image(img, Vec2::new(x, y), Some(Vec2::new(w, h)), None);
+}
+/// Draws the `sw` by `sh` pixel region at `(sx, sy)` of the image, for sprite sheets.
+#[allow(clippy::too_many_arguments)]
+pub fn image_sub(img: &PImage, x: f32, y: f32, w: f32, h: f32, sx: f32, sy: f32, sw: f32, sh: f32) {
+    let source = Rect::new(sx, sy, sx + sw, sy + sh);
+    send_image(img, Vec2::new(x, y), Some(Vec2::new(w, h)), Some(source));
+}
+
+fn send_image(img: &PImage, position: Vec2, extent: Option<Vec2>, source: Option<Rect>) {
+    let (mode, tint, layer) = with_state(|s| (s.image_mode, s.tint, s.style.layer));
+    send(ProcessingCommand::Image {
+        image: img.0.clone(),
+        position,
+        extent,
+        source,
+        mode,
+        tint: tint.unwrap_or(Color::WHITE),
+        transform: matrix(),
+        layer,
+    });
+}
+
+/// The canvas rectangle an image covers; without an `extent` it keeps its natural size.
+pub(super) fn destination(
+    position: Vec2,
+    extent: Option<Vec2>,
+    mode: ImageMode,
+    natural: Vec2,
+) -> Rect {
+    match (mode, extent) {
+        (ImageMode::Corners, Some(corner)) => Rect::from_corners(position, corner),
+        (ImageMode::Center, _) => Rect::from_center_size(position, extent.unwrap_or(natural)),
+        (ImageMode::Corner | ImageMode::Corners, _) => {
+            Rect::from_corners(position, position + extent.unwrap_or(natural))
+        }
+    }
+}
+
+/// An `image()` call waiting to be assigned to a pooled entity.
+pub(super) struct QueuedImage {
+    pub image: Handle<Image>,
+    pub position: Vec2,
+    pub extent: Option<Vec2>,
+    pub source: Option<Rect>,
+    pub mode: ImageMode,
+    pub tint: Color,
+    pub transform: Affine2,
+    /// Depth among the frame's shape batches, images and text, from 0 to 1.
+    pub z: f32,
+}
+
+#[derive(Component, Default)]
+pub(super) struct ImageQueue(pub Vec<QueuedImage>);
+
+/// The image entities of a canvas along with their meshes and materials,
+/// reused from frame to frame.
+#[derive(Component, Default)]
+pub(super) struct ImagePool(Vec<PooledQuad>);
+
+/// An image entity along with the mesh and material it owns.
+struct PooledQuad {
+    entity: Entity,
+    mesh: Handle<Mesh>,
+    material: Handle<ColorMaterial>,
+    shown: Shown,
+}
+
+/// What a pooled quad's mesh and material were last built to show.
+#[derive(PartialEq)]
+struct Shown {
+    image: AssetId<Image>,
+    corners: [Vec2; 4],
+    uv: Rect,
+    tint: Color,
+}
+
+/// Copies every image that loads or changes into each canvas's state, for
+/// `PImage::width()` and `load_pixels()`, which run without ECS access.
+pub(super) fn sync_images(
+    images: Res<Assets<Image>>,
+    mut events: EventReader<AssetEvent<Image>>,
+    mut canvases: Query<&mut SketchState>,
+    mut copies: Local<HashMap<AssetId<Image>, Arc<Image>>>,
+) {
+    let mut changed = false;
+    for event in events.read() {
+        match *event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => {
+                if let Some(image) = images.get(id) {
+                    copies.insert(id, Arc::new(image.clone()));
+                    changed = true;
+                }
+            }
+            AssetEvent::Removed { id } => changed |= copies.remove(&id).is_some(),
+            _ => {}
+        }
+    }
+    for mut state in &mut canvases {
+        if changed || state.is_added() {
+            state.images = copies.clone();
+        }
+    }
+}
+
+/// World-space corners (top-left, top-right, bottom-right, bottom-left) and UVs of
+/// the quad that shows `queued`, for an image `natural` pixels in size on a
+/// canvas of `size`.
+pub(super) fn quad(queued: &QueuedImage, natural: Vec2, size: Vec2) -> ([Vec2; 4], Rect) {
+    let source = queued
+        .source
+        .unwrap_or(Rect::from_corners(Vec2::ZERO, natural));
+    let dest = destination(queued.position, queued.extent, queued.mode, source.size());
+    let corners = [
+        dest.min,
+        Vec2::new(dest.max.x, dest.min.y),
+        dest.max,
+        Vec2::new(dest.min.x, dest.max.y),
+    ];
+    let world = to_world(&corners, &queued.transform, size);
+    (
+        [world[0], world[1], world[2], world[3]],
+        Rect::from_corners(source.min / natural, source.max / natural),
+    )
+}
+
+fn quad_mesh(corners: [Vec2; 4], uv: Rect) -> Mesh {
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        corners.map(|p| [p.x, p.y, 0.0]).to_vec(),
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; 4]);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_UV_0,
+        vec![
+            [uv.min.x, uv.min.y],
+            [uv.max.x, uv.min.y],
+            [uv.max.x, uv.max.y],
+            [uv.min.x, uv.max.y],
+        ],
+    );
+    mesh.insert_indices(Indices::U32(vec![0, 1, 2, 0, 2, 3]));
+    mesh
+}
+
+/// Shows each canvas's queued images, reusing entities, meshes and materials
+/// from earlier frames. A quad's mesh is only rebuilt when its corners or
+/// source rectangle change and its material when its image or tint does, so
+/// images that stay put aren't uploaded again every frame.
+pub(super) fn update_images(
+    mut commands: Commands,
+    mut canvases: Query<(
+        Entity,
+        &SketchState,
+        &mut ImageQueue,
+        &mut ImagePool,
+        Option<&RenderLayers>,
+    )>,
+    images: Res<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut entities: Query<(&mut Visibility, &mut Transform)>,
+) {
+    for (canvas, state, mut queue, mut pool, layers) in &mut canvases {
+        let pool = &mut pool.0;
+        let mut used = 0;
+        for queued in queue.0.drain(..) {
+            // Nothing to draw, or to size the quad by, until the image has loaded.
+            let Some(image) = images.get(&queued.image) else {
+                continue;
+            };
+            let (corners, uv) = quad(&queued, image.size_f32(), state.surface.size);
+            let shown = Shown {
+                image: queued.image.id(),
+                corners,
+                uv,
+                tint: queued.tint,
+            };
+            let material = || ColorMaterial {
+                color: queued.tint,
+                texture: Some(queued.image.clone()),
+                ..default()
+            };
+
+            if let Some(pooled) = pool.get_mut(used) {
+                if (pooled.shown.corners, pooled.shown.uv) != (corners, uv)
+                    && let Some(mesh) = meshes.get_mut(&pooled.mesh)
+                {
+                    *mesh = quad_mesh(corners, uv);
+                }
+                if (pooled.shown.image, pooled.shown.tint) != (shown.image, shown.tint)
+                    && let Some(m) = materials.get_mut(&pooled.material)
+                {
+                    *m = material();
+                }
+                pooled.shown = shown;
+                if let Ok((mut v, mut transform)) = entities.get_mut(pooled.entity) {
+                    v.set_if_neq(Visibility::Inherited);
+                    if transform.translation.z != queued.z {
+                        transform.translation.z = queued.z;
+                    }
+                }
+            } else {
+                let mesh = meshes.add(quad_mesh(corners, uv));
+                let material = materials.add(material());
+                let entity = commands
+                    .spawn((
+                        Mesh2d(mesh.clone()),
+                        MeshMaterial2d(material.clone()),
+                        Transform::from_xyz(0.0, 0.0, queued.z),
+                        NoFrustumCulling,
+                        layers.cloned().unwrap_or_default(),
+                        ChildOf(canvas),
+                    ))
+                    .id();
+                pool.push(PooledQuad {
+                    entity,
+                    mesh,
+                    material,
+                    shown,
+                });
+            }
+            used += 1;
+        }
+        for pooled in pool.iter().skip(used) {
+            if let Ok((mut v, _)) = entities.get_mut(pooled.entity) {
+                v.set_if_neq(Visibility::Hidden);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::sprite::AlphaMode2d;
+
+    use super::*;
+
+    fn queued(image: &Handle<Image>, position: Vec2, tint: Color) -> QueuedImage {
+        QueuedImage {
+            image: image.clone(),
+            position,
+            extent: None,
+            source: None,
+            mode: ImageMode::Corner,
+            tint,
+            transform: Affine2::IDENTITY,
+            z: 0.5,
+        }
+    }
+
+    #[test]
+    fn quads_are_only_rebuilt_when_they_change() {
+        let mut world = World::new();
+        world.init_resource::<Assets<Mesh>>();
+        world.init_resource::<Assets<ColorMaterial>>();
+        let image = world
+            .get_resource_or_init::<Assets<Image>>()
+            .add(Image::default());
+        let canvas = world
+            .spawn((
+                SketchState::default(),
+                ImageQueue::default(),
+                ImagePool::default(),
+            ))
+            .id();
+        let draw = |world: &mut World, position: Vec2, tint: Color| {
+            world.get_mut::<ImageQueue>(canvas).unwrap().0 = vec![queued(&image, position, tint)];
+            world.run_system_once(update_images).unwrap();
+            let pool = world.get::<ImagePool>(canvas).unwrap();
+            assert_eq!(pool.0.len(), 1);
+            (pool.0[0].mesh.clone(), pool.0[0].material.clone())
+        };
+        // Marks the quad's assets, so a rebuild shows as the mark going away.
+        let mark = |world: &mut World, mesh: &Handle<Mesh>, material: &Handle<ColorMaterial>| {
+            let mut meshes = world.resource_mut::<Assets<Mesh>>();
+            meshes
+                .get_mut(mesh)
+                .unwrap()
+                .remove_attribute(Mesh::ATTRIBUTE_NORMAL);
+            let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+            materials.get_mut(material).unwrap().alpha_mode = AlphaMode2d::Mask(0.5);
+        };
+        let marked = |world: &World, mesh: &Handle<Mesh>, material: &Handle<ColorMaterial>| {
+            let mesh = world.resource::<Assets<Mesh>>().get(mesh).unwrap();
+            let material = world
+                .resource::<Assets<ColorMaterial>>()
+                .get(material)
+                .unwrap();
+            (
+                !mesh.contains_attribute(Mesh::ATTRIBUTE_NORMAL),
+                material.alpha_mode == AlphaMode2d::Mask(0.5),
+            )
+        };
+
+        let (mesh, material) = draw(&mut world, Vec2::ZERO, Color::WHITE);
+        mark(&mut world, &mesh, &material);
+        let handles = draw(&mut world, Vec2::ZERO, Color::WHITE);
+        assert_eq!(handles, (mesh.clone(), material.clone()));
+        assert_eq!(marked(&world, &mesh, &material), (true, true));
+
+        draw(&mut world, Vec2::ONE, Color::WHITE);
+        assert_eq!(marked(&world, &mesh, &material), (false, true));
+
+        mark(&mut world, &mesh, &material);
+        draw(&mut world, Vec2::ONE, Color::BLACK);
+        assert_eq!(marked(&world, &mesh, &material), (true, false));
+    }
+}
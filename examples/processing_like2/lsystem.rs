@@ -0,0 +1,158 @@
+//! L-systems: an axiom rewritten by rules, generation after generation, then
+//! read as moves of a `Turtle`. A few rules grow snowflakes, dragon curves
+//! and plants.
+
+use std::collections::HashMap;
+
+use super::turtle::Turtle;
+
+/// An L-system and the sentence it has grown to so far. The turtle reads the
+/// sentence one symbol at a time:
+///
+/// - `F` and `G` move forward one step, drawing a line.
+/// - `f` moves forward one step without drawing.
+/// - `+` turns left and `-` turns right by the angle.
+/// - `|` turns around.
+/// - `[` saves the turtle's position and heading and `]` returns to them.
+///
+/// Other symbols only stand for something to be rewritten.
+#[derive(Clone, Debug)]
+pub struct LSystem {
+    axiom: String,
+    rules: HashMap<char, String>,
+    /// Degrees turned by `+` and `-`.
+    angle: f32,
+    sentence: String,
+    generation: u32,
+    /// How many `F`s and `G`s are in `sentence`.
+    segments: usize,
+}
+
+impl LSystem {
+    /// An L-system starting from `axiom`, turning by `angle` degrees. Add its
+    /// rules with `rule()`.
+    pub fn new(axiom: &str, angle: f32) -> Self {
+        let mut system = Self {
+            axiom: axiom.to_owned(),
+            rules: HashMap::new(),
+            angle,
+            sentence: String::new(),
+            generation: 0,
+            segments: 0,
+        };
+        system.reset();
+        system
+    }
+
+    /// Rewrites every `symbol` as `replacement` from one generation to the next.
+    pub fn rule(mut self, symbol: char, replacement: &str) -> Self {
+        self.rules.insert(symbol, replacement.to_owned());
+        self
+    }
+
+    /// Koch's snowflake: every edge of a triangle grows a smaller triangle.
+    /// Each generation, draw with a third of the step to keep the size.
+    pub fn koch() -> Self {
+        Self::new("F--F--F", 60.0).rule('F', "F+F--F+F")
+    }
+
+    /// Heighway's dragon curve, a strip of paper folded in half again and
+    /// again. Each generation, draw with the step over √2 to keep the size.
+    pub fn dragon() -> Self {
+        Self::new("FX", 90.0).rule('X', "X+YF+").rule('Y', "-FX-Y")
+    }
+
+    /// A branching plant, from Prusinkiewicz and Lindenmayer's "The
+    /// Algorithmic Beauty of Plants". Start the turtle facing up and about 25°
+    /// to the right; each generation, draw with half the step to keep the size.
+    pub fn plant() -> Self {
+        Self::new("X", 25.0)
+            .rule('X', "F+[[X]-X]-F[-FX]+X")
+            .rule('F', "FF")
+    }
+
+    /// Goes back to generation 0, the axiom.
+    pub fn reset(&mut self) {
+        self.sentence.clone_from(&self.axiom);
+        self.generation = 0;
+        self.count_segments();
+    }
+
+    /// Grows the next generation by rewriting every symbol with a rule at once.
+    pub fn iterate(&mut self) {
+        let mut next = String::with_capacity(self.sentence.len() * 2);
+        for symbol in self.sentence.chars() {
+            match self.rules.get(&symbol) {
+                Some(replacement) => next.push_str(replacement),
+                None => next.push(symbol),
+            }
+        }
+        self.sentence = next;
+        self.generation += 1;
+        self.count_segments();
+    }
+
+    /// Grows or goes back to `generation`.
+    pub fn iterate_to(&mut self, generation: u32) {
+        if generation < self.generation {
+            self.reset();
+        }
+        while self.generation < generation {
+            self.iterate();
+        }
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// How many lines `draw()` draws.
+    pub fn segments(&self) -> usize {
+        self.segments
+    }
+
+    fn count_segments(&mut self) {
+        self.segments = self
+            .sentence
+            .chars()
+            .filter(|c| matches!(c, 'F' | 'G'))
+            .count();
+    }
+
+    /// Walks `turtle` through the sentence, `step` pixels per move.
+    pub fn draw(&self, turtle: &mut Turtle, step: f32) {
+        self.draw_partial(turtle, step, 1.0);
+    }
+
+    /// Like `draw()`, but stops once `amount` (0 to 1) of the lines are drawn,
+    /// partway along the last one. Raising `amount` a little every frame
+    /// shows the shape growing in the order the turtle draws it.
+    pub fn draw_partial(&self, turtle: &mut Turtle, step: f32, amount: f32) {
+        let mut left = amount.clamp(0.0, 1.0) * self.segments as f32;
+        for symbol in self.sentence.chars() {
+            match symbol {
+                'F' | 'G' => {
+                    if left <= 0.0 {
+                        break;
+                    }
+                    turtle.forward(step * left.min(1.0));
+                    left -= 1.0;
+                }
+                'f' => {
+                    let pen_down = turtle.is_pen_down();
+                    turtle.pen_up();
+                    turtle.forward(step);
+                    if pen_down {
+                        turtle.pen_down();
+                    }
+                }
+                '+' => turtle.left(self.angle),
+                '-' => turtle.right(self.angle),
+                '|' => turtle.left(180.0),
+                '[' => turtle.push(),
+                ']' => turtle.pop(),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,2423 @@
+use bevy::ecs::system::SystemParam;
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::mouse::{MouseButtonInput, MouseScrollUnit, MouseWheel};
+use bevy::math::Affine2;
+use bevy::prelude::*;
+use bevy::render::camera::{ScalingMode, Viewport};
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::view::{NoFrustumCulling, RenderLayers};
+use bevy::window::{MonitorSelection, PrimaryWindow, WindowMode};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+mod blend;
+mod color;
+mod curves;
+mod graphics;
+mod image;
+mod lsystem;
+mod math;
+mod pacing;
+mod pixels;
+mod script;
+mod sketch_file;
+mod software;
+mod stroke;
+mod svg;
+mod text;
+mod triangulate;
+mod turtle;
+
+use blend::{BlendMaterial, BlendMaterials, BlendMode, BlendPlugin, blend_mode};
+use color::{
+    ColorMode, ColorSettings, color, color_alpha, color_mode, color_mode_max, gray, gray_alpha,
+    hex_color, lerp_color,
+};
+use curves::{elliptical_arc, flatten_catmull_rom, flatten_cubic, flatten_quadratic};
+use graphics::{LayerCamera, PGraphics, create_graphics, spawn_graphics, sync_graphics};
+use image::{
+    ImageMode, ImagePool, ImageQueue, PImage, QueuedImage, image, image_mode, image_sized,
+    image_sub, load_image, no_tint, sync_images, tint, update_images,
+};
+use lsystem::LSystem;
+use math::{
+    RandomState, constrain, dist, lerp, map, noise, noise_detail, noise_seed, random,
+    random_gaussian, random_seed,
+};
+use pacing::{Pacing, frame_rate, is_looping, r#loop, no_loop, redraw, run_draws};
+use pixels::{Pixels, update_pixels, upload_pixels, with_pixels};
+use script::{Script, ScriptLoader, ScriptSource, run_scripts};
+use sketch_file::{SketchFile, SketchFileLoader, SketchSource, draw_sketch_files, report_reloads};
+use software::{headless_from_args, save, save_frame, save_frames};
+use stroke::{Pen, StrokeCap, StrokeJoin, stroke_cap, stroke_join, tessellate_stroke};
+use svg::{RecordFormat, Recording, begin_record, end_record, record_frame};
+use text::{
+    PFont, QueuedText, TextAlign, TextBaseline, TextPool, TextQueue, TextStyle, load_font, text,
+    text_align, text_font, text_leading, text_size, text_width, update_text,
+};
+use triangulate::triangulate;
+use turtle::Turtle;
+
+/// Canvas size until the sketch calls `size()`.
+const DEFAULT_SIZE: Vec2 = Vec2::new(400.0, 400.0);
+
+fn canvas_to_world(p: Vec2, size: Vec2) -> Vec2 {
+    Vec2::new(p.x - size.x * 0.5, size.y * 0.5 - p.y)
+}
+
+fn world_to_canvas(p: Vec2, size: Vec2) -> Vec2 {
+    Vec2::new(p.x + size.x * 0.5, size.y * 0.5 - p.y)
+}
+
+/// Processing's global drawing style, captured into every shape command.
+#[derive(Clone, Copy)]
+struct Style {
+    fill: Option<Color>,
+    stroke: Option<Color>,
+    stroke_weight: f32,
+    /// Largest on-screen distance, in pixels, between a curve and its polyline.
+    curve_tolerance: f32,
+    blend: BlendMode,
+    rect_mode: ShapeMode,
+    ellipse_mode: ShapeMode,
+    pen: Pen,
+    /// Set by `layer()`; higher layers are drawn over lower ones.
+    layer: i32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fill: Some(Color::WHITE),
+            stroke: Some(Color::BLACK),
+            stroke_weight: 1.0,
+            curve_tolerance: 0.25,
+            blend: BlendMode::Blend,
+            rect_mode: ShapeMode::Corner,
+            ellipse_mode: ShapeMode::Center,
+            pen: Pen::default(),
+            layer: 0,
+        }
+    }
+}
+
+/// The canvas size from `size()` and whether the window should fill the screen.
+#[derive(Clone, Copy)]
+struct Surface {
+    size: Vec2,
+    full_screen: bool,
+}
+
+impl Default for Surface {
+    fn default() -> Self {
+        Self {
+            size: DEFAULT_SIZE,
+            full_screen: false,
+        }
+    }
+}
+
+/// How `vertex()` calls between `begin_shape()` and `end_shape()` are connected.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ShapeKind {
+    /// A single polygon, possibly concave, with holes from `begin_contour()`.
+    #[default]
+    Polygon,
+    Points,
+    Lines,
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+    Quads,
+    QuadStrip,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EndShape {
+    Open,
+    Close,
+}
+
+/// How `rect_mode()` and `ellipse_mode()` read the four numbers that place a
+/// rectangle or an ellipse.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShapeMode {
+    /// The top-left corner, then the width and height.
+    Corner,
+    /// Two opposite corners.
+    Corners,
+    /// The centre, then the width and height.
+    Center,
+    /// The centre, then half the width and half the height.
+    Radius,
+}
+
+impl ShapeMode {
+    /// The bounds `(a, b, c, d)` stand for in this mode. Negative sizes and
+    /// corners given in any order are flipped around, as in Processing.
+    fn bounds(self, a: f32, b: f32, c: f32, d: f32) -> Rect {
+        let (p, q) = (Vec2::new(a, b), Vec2::new(c, d));
+        match self {
+            ShapeMode::Corner => Rect::from_corners(p, p + q),
+            ShapeMode::Corners => Rect::from_corners(p, q),
+            ShapeMode::Center => Rect::from_center_size(p, q.abs()),
+            ShapeMode::Radius => Rect::from_center_half_size(p, q.abs()),
+        }
+    }
+}
+
+/// How `arc()` is filled and outlined.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArcMode {
+    /// Filled up to the chord, but only the curved edge is stroked.
+    Open,
+    /// Filled and stroked as a closed segment.
+    Chord,
+    /// Filled and stroked as a wedge back to the centre.
+    Pie,
+}
+
+#[derive(Clone)]
+enum ProcessingCommand {
+    Point {
+        x: f32,
+        y: f32,
+        style: Style,
+        transform: Affine2,
+    },
+    Line {
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        style: Style,
+        transform: Affine2,
+    },
+    /// Always from the top-left corner, whatever the `rect_mode()`.
+    Rect {
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        /// Corner radii clockwise from the top left, at most half the shorter side.
+        radii: [f32; 4],
+        style: Style,
+        transform: Affine2,
+    },
+    /// Always from the centre, whatever the `ellipse_mode()`.
+    Ellipse {
+        cx: f32,
+        cy: f32,
+        w: f32,
+        h: f32,
+        style: Style,
+        transform: Affine2,
+    },
+    Triangle {
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        x3: f32,
+        y3: f32,
+        style: Style,
+        transform: Affine2,
+    },
+    Quad {
+        corners: [Vec2; 4],
+        style: Style,
+        transform: Affine2,
+    },
+    Arc {
+        cx: f32,
+        cy: f32,
+        w: f32,
+        h: f32,
+        start: f32,
+        stop: f32,
+        mode: ArcMode,
+        style: Style,
+        transform: Affine2,
+    },
+    Shape {
+        kind: ShapeKind,
+        /// The outline first, then one entry per `begin_contour()` hole.
+        contours: Vec<Vec<Vec2>>,
+        close: bool,
+        style: Style,
+        transform: Affine2,
+    },
+    Image {
+        image: Handle<Image>,
+        position: Vec2,
+        /// Width and height, or the opposite corner in `ImageMode::Corners`.
+        extent: Option<Vec2>,
+        /// Region of the image to draw, in pixels; `None` draws all of it.
+        source: Option<Rect>,
+        mode: ImageMode,
+        tint: Color,
+        transform: Affine2,
+        layer: i32,
+    },
+    Text {
+        content: String,
+        x: f32,
+        y: f32,
+        text_style: TextStyle,
+        style: Style,
+        transform: Affine2,
+    },
+    Background {
+        color: Color,
+    },
+}
+
+impl ProcessingCommand {
+    /// The blend mode of a command drawn into the shape mesh.
+    fn blend_mode(&self) -> Option<BlendMode> {
+        match self {
+            ProcessingCommand::Point { style, .. }
+            | ProcessingCommand::Line { style, .. }
+            | ProcessingCommand::Rect { style, .. }
+            | ProcessingCommand::Ellipse { style, .. }
+            | ProcessingCommand::Triangle { style, .. }
+            | ProcessingCommand::Quad { style, .. }
+            | ProcessingCommand::Arc { style, .. }
+            | ProcessingCommand::Shape { style, .. } => Some(style.blend),
+            ProcessingCommand::Image { .. }
+            | ProcessingCommand::Text { .. }
+            | ProcessingCommand::Background { .. } => None,
+        }
+    }
+
+    /// The `layer()` a command was drawn on.
+    fn layer(&self) -> i32 {
+        match self {
+            ProcessingCommand::Point { style, .. }
+            | ProcessingCommand::Line { style, .. }
+            | ProcessingCommand::Rect { style, .. }
+            | ProcessingCommand::Ellipse { style, .. }
+            | ProcessingCommand::Triangle { style, .. }
+            | ProcessingCommand::Quad { style, .. }
+            | ProcessingCommand::Arc { style, .. }
+            | ProcessingCommand::Shape { style, .. }
+            | ProcessingCommand::Text { style, .. } => style.layer,
+            ProcessingCommand::Image { layer, .. } => *layer,
+            ProcessingCommand::Background { .. } => 0,
+        }
+    }
+}
+
+fn send(cmd: ProcessingCommand) {
+    with_state(|s| s.commands.push(cmd));
+}
+
+/// Everything about one canvas that outlives a single sketch call. It lives on
+/// the canvas entity and is swapped into `STATE` while sketch code runs.
+#[derive(Component, Clone, Default)]
+struct SketchState {
+    /// Drawn since the last frame was rendered, in call order.
+    commands: Vec<ProcessingCommand>,
+    /// The commands of the last frame drawn, shown again on frames that aren't.
+    shown: Vec<ProcessingCommand>,
+    frame_count: u32,
+    millis: u32,
+    style: Style,
+    matrix: Affine2,
+    matrix_stack: Vec<Affine2>,
+    shape: Option<ShapeRecorder>,
+    input: InputState,
+    text: TextStyle,
+    asset_server: Option<AssetServer>,
+    /// Raw bytes of every loaded font, so `text_width()` can measure without ECS access.
+    font_data: HashMap<AssetId<Font>, Arc<Vec<u8>>>,
+    image_mode: ImageMode,
+    tint: Option<Color>,
+    /// A copy of every loaded image, for `PImage::width()` and `height()` and
+    /// for `load_pixels()` to draw with.
+    images: HashMap<AssetId<Image>, Arc<Image>>,
+    color: ColorSettings,
+    random: RandomState,
+    /// Paths passed to `save()` this frame, written once the frame is drawn.
+    saves: Vec<String>,
+    recording: Option<Recording>,
+    surface: Surface,
+    /// Layers from `create_graphics()` still to be spawned.
+    graphics: Vec<PGraphics>,
+    pixels: Pixels,
+    pacing: Pacing,
+}
+
+/// Mouse and keyboard state as seen by the sketch, in canvas coordinates.
+#[derive(Clone, Default)]
+struct InputState {
+    mouse: Vec2,
+    pmouse: Vec2,
+    mouse_pressed: bool,
+    mouse_button: Option<MouseButton>,
+    key: Option<char>,
+    key_code: Option<KeyCode>,
+    key_pressed: bool,
+    /// Whether this is the topmost canvas under the cursor, the one that gets
+    /// the mouse events.
+    under_cursor: bool,
+    /// Whether this canvas gets the key events: the last one clicked, or the
+    /// `WindowCanvas` until another is.
+    focused: bool,
+}
+
+/// Vertices collected between `begin_shape()` and `end_shape()`.
+#[derive(Clone)]
+struct ShapeRecorder {
+    kind: ShapeKind,
+    contours: Vec<Vec<PathVertex>>,
+    in_contour: bool,
+}
+
+/// A `vertex()`-family call, kept unflattened until `end_shape()` knows the transform.
+#[derive(Clone, Copy)]
+enum PathVertex {
+    Vertex(Vec2),
+    Bezier(Vec2, Vec2, Vec2),
+    Quadratic(Vec2, Vec2),
+    Curve(Vec2),
+}
+
+/// Turns one contour into a polyline, following Processing's rules: runs of
+/// `curve_vertex()` need four points and draw between the inner ones, and
+/// Bézier segments start from the previous point.
+fn flatten_contour(path: &[PathVertex], tolerance: f32) -> Vec<Vec2> {
+    let mut out: Vec<Vec2> = Vec::new();
+    let mut i = 0;
+    while i < path.len() {
+        match path[i] {
+            PathVertex::Vertex(p) => out.push(p),
+            PathVertex::Bezier(c1, c2, p) => match out.last() {
+                Some(&from) => flatten_cubic(&mut out, from, c1, c2, p, tolerance),
+                None => out.push(p),
+            },
+            PathVertex::Quadratic(c, p) => match out.last() {
+                Some(&from) => flatten_quadratic(&mut out, from, c, p, tolerance),
+                None => out.push(p),
+            },
+            PathVertex::Curve(_) => {
+                let run: Vec<Vec2> = path[i..]
+                    .iter()
+                    .map_while(|v| match v {
+                        PathVertex::Curve(p) => Some(*p),
+                        _ => None,
+                    })
+                    .collect();
+                if run.len() >= 4 {
+                    out.push(run[1]);
+                    for w in run.windows(4) {
+                        flatten_catmull_rom(&mut out, [w[0], w[1], w[2], w[3]], tolerance);
+                    }
+                }
+                i += run.len();
+                continue;
+            }
+        }
+        i += 1;
+    }
+    out
+}
+
+// Sketch functions take no context argument: they all reach the canvas being
+// drawn through `STATE`, which `run_user` swaps a canvas's state into on the
+// thread running its sketch code.
+thread_local! {
+    static STATE: RefCell<SketchState> = RefCell::new(SketchState::default());
+    /// Whether `STATE` holds a canvas's state rather than a placeholder.
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+}
+fn with_state<R>(f: impl FnOnce(&mut SketchState) -> R) -> R {
+    if !ACTIVE.get() {
+        warn_once!("sketch functions draw nothing outside a Sketch or SketchContext::draw()");
+    }
+    STATE.with(|c| f(&mut c.borrow_mut()))
+}
+fn style() -> Style {
+    with_state(|s| s.style)
+}
+fn matrix() -> Affine2 {
+    with_state(|s| s.matrix)
+}
+
+/// Number of times `draw` has run; 0 inside `setup`, 1 during the first `draw`.
+pub fn frame_count() -> u32 {
+    with_state(|s| s.frame_count)
+}
+/// Milliseconds elapsed since the sketch started.
+pub fn millis() -> u32 {
+    with_state(|s| s.millis)
+}
+pub fn mouse_x() -> f32 {
+    with_state(|s| s.input.mouse.x)
+}
+pub fn mouse_y() -> f32 {
+    with_state(|s| s.input.mouse.y)
+}
+/// Mouse position during the previous frame.
+pub fn pmouse_x() -> f32 {
+    with_state(|s| s.input.pmouse.x)
+}
+pub fn pmouse_y() -> f32 {
+    with_state(|s| s.input.pmouse.y)
+}
+pub fn mouse_is_pressed() -> bool {
+    with_state(|s| s.input.mouse_pressed)
+}
+/// The button most recently pressed or released.
+pub fn mouse_button() -> Option<MouseButton> {
+    with_state(|s| s.input.mouse_button)
+}
+/// The character of the key most recently pressed or released, if it has one.
+pub fn key() -> Option<char> {
+    with_state(|s| s.input.key)
+}
+/// The physical key most recently pressed or released, for keys without a character.
+pub fn key_code() -> Option<KeyCode> {
+    with_state(|s| s.input.key_code)
+}
+pub fn key_is_pressed() -> bool {
+    with_state(|s| s.input.key_pressed)
+}
+/// Whether the canvas is the one that gets the mouse events.
+fn is_under_cursor() -> bool {
+    with_state(|s| s.input.under_cursor)
+}
+
+/// Sets the canvas size. On the canvas shown in the window, the window is
+/// resized to match; the canvas keeps this size however the window changes.
+pub fn size(w: f32, h: f32) {
+    with_state(|s| s.surface.size = Vec2::new(w, h).max(Vec2::ONE));
+}
+pub fn width() -> f32 {
+    with_state(|s| s.surface.size.x)
+}
+pub fn height() -> f32 {
+    with_state(|s| s.surface.size.y)
+}
+/// Makes the window fill the screen, with the canvas scaled up to fit and
+/// letterboxed. Only affects the canvas shown in the window.
+pub fn full_screen() {
+    with_state(|s| s.surface.full_screen = true);
+}
+
+pub fn background(color: Color) {
+    send(ProcessingCommand::Background { color });
+}
+
+pub fn fill(color: Color) {
+    with_state(|s| s.style.fill = Some(color));
+}
+pub fn no_fill() {
+    with_state(|s| s.style.fill = None);
+}
+pub fn stroke(color: Color) {
+    with_state(|s| s.style.stroke = Some(color));
+}
+pub fn no_stroke() {
+    with_state(|s| s.style.stroke = None);
+}
+pub fn stroke_weight(weight: f32) {
+    with_state(|s| s.style.stroke_weight = weight);
+}
+/// Sets how `rect()` and `square()` read their numbers; `ShapeMode::Corner` by default.
+pub fn rect_mode(mode: ShapeMode) {
+    with_state(|s| s.style.rect_mode = mode);
+}
+/// Sets how `ellipse()`, `circle()` and `arc()` read their numbers;
+/// `ShapeMode::Center` by default.
+pub fn ellipse_mode(mode: ShapeMode) {
+    with_state(|s| s.style.ellipse_mode = mode);
+}
+/// Draws everything from now on, including images and text, on layer `n`:
+/// over whatever is on lower layers and under higher ones, whatever the call
+/// order. Within a layer, later calls draw over earlier ones. Sketches start
+/// on layer 0; a HUD might use `layer(1)`.
+pub fn layer(n: i32) {
+    with_state(|s| s.style.layer = n);
+}
+/// Sets how far, in pixels, curves and ellipses may deviate from their true outline.
+/// Smaller values give smoother curves at the cost of more triangles.
+pub fn curve_tolerance(pixels: f32) {
+    with_state(|s| s.style.curve_tolerance = pixels.max(0.01));
+}
+
+pub fn push_matrix() {
+    with_state(|s| {
+        let m = s.matrix;
+        s.matrix_stack.push(m);
+    });
+}
+pub fn pop_matrix() {
+    with_state(|s| match s.matrix_stack.pop() {
+        Some(m) => s.matrix = m,
+        None => warn!("pop_matrix() called more times than push_matrix()"),
+    });
+}
+pub fn reset_matrix() {
+    with_state(|s| s.matrix = Affine2::IDENTITY);
+}
+pub fn translate(x: f32, y: f32) {
+    with_state(|s| s.matrix *= Affine2::from_translation(Vec2::new(x, y)));
+}
+/// Rotates by `angle` radians; positive angles turn clockwise on the y-down canvas.
+pub fn rotate(angle: f32) {
+    with_state(|s| s.matrix *= Affine2::from_angle(angle));
+}
+pub fn scale(sx: f32, sy: f32) {
+    with_state(|s| s.matrix *= Affine2::from_scale(Vec2::new(sx, sy)));
+}
+
+pub fn begin_shape(kind: ShapeKind) {
+    with_state(|s| {
+        s.shape = Some(ShapeRecorder {
+            kind,
+            contours: vec![Vec::new()],
+            in_contour: false,
+        });
+    });
+}
+fn push_vertex(v: PathVertex) {
+    with_state(|s| match &mut s.shape {
+        Some(shape) => {
+            let contour = if shape.in_contour {
+                shape.contours.len() - 1
+            } else {
+                0
+            };
+            shape.contours[contour].push(v);
+        }
+        None => warn!("vertex() called outside begin_shape()/end_shape()"),
+    });
+}
+pub fn vertex(x: f32, y: f32) {
+    push_vertex(PathVertex::Vertex(Vec2::new(x, y)));
+}
+/// Adds a cubic Bézier from the previous vertex to `(x, y)`.
+pub fn bezier_vertex(cx1: f32, cy1: f32, cx2: f32, cy2: f32, x: f32, y: f32) {
+    push_vertex(PathVertex::Bezier(
+        Vec2::new(cx1, cy1),
+        Vec2::new(cx2, cy2),
+        Vec2::new(x, y),
+    ));
+}
+/// Adds a quadratic Bézier from the previous vertex to `(x, y)`.
+pub fn quadratic_vertex(cx: f32, cy: f32, x: f32, y: f32) {
+    push_vertex(PathVertex::Quadratic(Vec2::new(cx, cy), Vec2::new(x, y)));
+}
+/// Adds a Catmull-Rom spline point; the first and last of a run only shape the ends.
+pub fn curve_vertex(x: f32, y: f32) {
+    push_vertex(PathVertex::Curve(Vec2::new(x, y)));
+}
+/// Starts a hole in the current `ShapeKind::Polygon`; its vertices should wind
+/// opposite to the outline, as in Processing, though either direction works here.
+pub fn begin_contour() {
+    with_state(|s| match &mut s.shape {
+        Some(shape) => {
+            shape.contours.push(Vec::new());
+            shape.in_contour = true;
+        }
+        None => warn!("begin_contour() called outside begin_shape()/end_shape()"),
+    });
+}
+pub fn end_contour() {
+    with_state(|s| {
+        if let Some(shape) = &mut s.shape {
+            shape.in_contour = false;
+        }
+    });
+}
+pub fn end_shape(mode: EndShape) {
+    let Some(shape) = with_state(|s| s.shape.take()) else {
+        warn!("end_shape() called without begin_shape()");
+        return;
+    };
+    let style = style();
+    let transform = matrix();
+    let tolerance = style.curve_tolerance / transform_scale(&transform);
+    send(ProcessingCommand::Shape {
+        kind: shape.kind,
+        contours: shape
+            .contours
+            .iter()
+            .map(|c| flatten_contour(c, tolerance))
+            .collect(),
+        close: mode == EndShape::Close,
+        style,
+        transform,
+    });
+}
+
+/// Draws a dot as wide as the stroke weight, in the stroke color.
+pub fn point(x: f32, y: f32) {
+    send(ProcessingCommand::Point {
+        x,
+        y,
+        style: style(),
+        transform: matrix(),
+    });
+}
+pub fn line(x1: f32, y1: f32, x2: f32, y2: f32) {
+    send(ProcessingCommand::Line {
+        x1,
+        y1,
+        x2,
+        y2,
+        style: style(),
+        transform: matrix(),
+    });
+}
+/// Draws a rectangle placed according to `rect_mode()`, from the top-left
+/// corner by default.
+pub fn rect(a: f32, b: f32, c: f32, d: f32) {
+    rect_radii(a, b, c, d, 0.0, 0.0, 0.0, 0.0);
+}
+/// Draws a rectangle with every corner rounded to radius `r`.
+pub fn rect_rounded(a: f32, b: f32, c: f32, d: f32, r: f32) {
+    rect_radii(a, b, c, d, r, r, r, r);
+}
+/// Draws a rectangle with its own radius for each corner, clockwise from the
+/// top left. Radii beyond half the shorter side are reduced to that.
+#[allow(clippy::too_many_arguments)]
+pub fn rect_radii(a: f32, b: f32, c: f32, d: f32, tl: f32, tr: f32, br: f32, bl: f32) {
+    let style = style();
+    let bounds = style.rect_mode.bounds(a, b, c, d);
+    let max = bounds.size().min_element() * 0.5;
+    send(ProcessingCommand::Rect {
+        x: bounds.min.x,
+        y: bounds.min.y,
+        w: bounds.width(),
+        h: bounds.height(),
+        radii: [tl, tr, br, bl].map(|r| r.clamp(0.0, max)),
+        style,
+        transform: matrix(),
+    });
+}
+/// Draws a square placed according to `rect_mode()`.
+pub fn square(x: f32, y: f32, extent: f32) {
+    rect(x, y, extent, extent);
+}
+/// Draws an ellipse placed according to `ellipse_mode()`, from the centre by default.
+pub fn ellipse(a: f32, b: f32, c: f32, d: f32) {
+    let style = style();
+    let bounds = style.ellipse_mode.bounds(a, b, c, d);
+    send(ProcessingCommand::Ellipse {
+        cx: bounds.center().x,
+        cy: bounds.center().y,
+        w: bounds.width(),
+        h: bounds.height(),
+        style,
+        transform: matrix(),
+    });
+}
+/// Draws a circle placed according to `ellipse_mode()`; `extent` is the
+/// diameter, or the radius in `ShapeMode::Radius`.
+pub fn circle(x: f32, y: f32, extent: f32) {
+    ellipse(x, y, extent, extent);
+}
+/// Draws the part of an ellipse between `start` and `stop` radians, measured
+/// clockwise from the positive x axis like every other angle on the canvas.
+/// The ellipse is placed according to `ellipse_mode()`. As in Processing,
+/// nothing is drawn unless `stop` is past `start`.
+pub fn arc(a: f32, b: f32, c: f32, d: f32, start: f32, stop: f32, mode: ArcMode) {
+    if stop <= start {
+        return;
+    }
+    let style = style();
+    let bounds = style.ellipse_mode.bounds(a, b, c, d);
+    send(ProcessingCommand::Arc {
+        cx: bounds.center().x,
+        cy: bounds.center().y,
+        w: bounds.width(),
+        h: bounds.height(),
+        start,
+        stop,
+        mode,
+        style,
+        transform: matrix(),
+    });
+}
+/// Draws a cubic Bézier from `(x1, y1)` to `(x2, y2)`.
+#[allow(clippy::too_many_arguments)]
+pub fn bezier(x1: f32, y1: f32, cx1: f32, cy1: f32, cx2: f32, cy2: f32, x2: f32, y2: f32) {
+    begin_shape(ShapeKind::Polygon);
+    vertex(x1, y1);
+    bezier_vertex(cx1, cy1, cx2, cy2, x2, y2);
+    end_shape(EndShape::Open);
+}
+/// Draws the Catmull-Rom segment between the middle two points.
+#[allow(clippy::too_many_arguments)]
+pub fn curve(x1: f32, y1: f32, x2: f32, y2: f32, x3: f32, y3: f32, x4: f32, y4: f32) {
+    begin_shape(ShapeKind::Polygon);
+    curve_vertex(x1, y1);
+    curve_vertex(x2, y2);
+    curve_vertex(x3, y3);
+    curve_vertex(x4, y4);
+    end_shape(EndShape::Open);
+}
+pub fn triangle(x1: f32, y1: f32, x2: f32, y2: f32, x3: f32, y3: f32) {
+    send(ProcessingCommand::Triangle {
+        x1,
+        y1,
+        x2,
+        y2,
+        x3,
+        y3,
+        style: style(),
+        transform: matrix(),
+    });
+}
+/// Draws a four-sided polygon through the corners in order. It may be concave.
+#[allow(clippy::too_many_arguments)]
+pub fn quad(x1: f32, y1: f32, x2: f32, y2: f32, x3: f32, y3: f32, x4: f32, y4: f32) {
+    send(ProcessingCommand::Quad {
+        corners: [
+            Vec2::new(x1, y1),
+            Vec2::new(x2, y2),
+            Vec2::new(x3, y3),
+            Vec2::new(x4, y4),
+        ],
+        style: style(),
+        transform: matrix(),
+    });
+}
+
+/// The meshes a canvas's frame is batched into, one per run of shapes in the
+/// same blend mode, rewritten in place each frame instead of spawning an entity
+/// per shape.
+#[derive(Component, Default)]
+struct SketchMesh {
+    batches: Vec<MeshBatch>,
+    /// How many of `batches` hold this frame's shapes; the rest are hidden.
+    used: usize,
+}
+
+struct MeshBatch {
+    entity: Entity,
+    mesh: Handle<Mesh>,
+    mode: BlendMode,
+    z: f32,
+}
+
+/// Triangles for a run of shapes, with fill and stroke colors baked into the
+/// vertices. Shapes are appended in call order, which is also the order the
+/// GPU draws them in, so later shapes land on top.
+#[derive(Default)]
+struct MeshBuilder {
+    /// Canvas size, for mapping canvas coordinates to world space.
+    size: Vec2,
+    mode: BlendMode,
+    /// Depth among the frame's batches, images and text, from 0 at the back to 1.
+    z: f32,
+    positions: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl MeshBuilder {
+    fn push_vertex(&mut self, p: Vec2, color: Color) -> u32 {
+        self.positions.push([p.x, p.y, 0.0]);
+        self.colors.push(color.to_linear().to_f32_array());
+        self.positions.len() as u32 - 1
+    }
+
+    /// Fills a convex polygon as a triangle fan.
+    fn fill(&mut self, points: &[Vec2], color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+        let base = self.positions.len() as u32;
+        for &p in points {
+            self.push_vertex(p, color);
+        }
+        for i in 1..points.len() as u32 - 1 {
+            self.indices.extend([base, base + i, base + i + 1]);
+        }
+    }
+
+    /// Fills triangles given as indices into `points`.
+    fn fill_indexed(&mut self, points: &[Vec2], indices: &[u32], color: Color) {
+        let base = self.positions.len() as u32;
+        for &p in points {
+            self.push_vertex(p, color);
+        }
+        self.indices.extend(indices.iter().map(|i| base + i));
+    }
+
+    /// Outlines a polyline in world coordinates with a band `weight` wide
+    /// centred on it, capped and joined as `style` says.
+    fn stroke(&mut self, points: &[Vec2], closed: bool, weight: f32, color: Color, style: &Style) {
+        let (vertices, indices) =
+            tessellate_stroke(points, closed, weight, &style.pen, style.curve_tolerance);
+        self.fill_indexed(&vertices, &indices, color);
+    }
+
+    /// Fills and strokes a convex shape given in canvas coordinates under `transform`.
+    fn shape(&mut self, points: &[Vec2], closed: bool, style: &Style, transform: &Affine2) {
+        let points = to_world(points, transform, self.size);
+        if let Some(color) = style.fill {
+            self.fill(&points, color);
+        }
+        if let Some(color) = style.stroke {
+            let weight = style.stroke_weight * transform_scale(transform);
+            self.stroke(&points, closed, weight, color, style);
+        }
+    }
+
+    /// Draws a point: a disc as wide as the stroke weight, in the stroke color.
+    fn dot(&mut self, p: Vec2, style: &Style, transform: &Affine2) {
+        let Some(color) = style.stroke else {
+            return;
+        };
+        let dot = Style {
+            fill: Some(color),
+            stroke: None,
+            ..*style
+        };
+        let tolerance = local_tolerance(style, transform);
+        let d = style.stroke_weight;
+        self.shape(&ellipse_points(p, d, d, tolerance), true, &dot, transform);
+    }
+
+    /// Fills a possibly concave polygon with holes and strokes each of its contours.
+    fn polygon(&mut self, contours: &[Vec<Vec2>], close: bool, style: &Style, transform: &Affine2) {
+        let contours: Vec<Vec<Vec2>> = contours
+            .iter()
+            .filter(|c| !c.is_empty())
+            .map(|c| to_world(c, transform, self.size))
+            .collect();
+        let Some((outer, holes)) = contours.split_first() else {
+            return;
+        };
+        if let Some(color) = style.fill {
+            let indices = triangulate(outer, holes);
+            self.fill_indexed(&contours.concat(), &indices, color);
+        }
+        if let Some(color) = style.stroke {
+            let weight = style.stroke_weight * transform_scale(transform);
+            self.stroke(outer, close, weight, color, style);
+            for hole in holes {
+                self.stroke(hole, true, weight, color, style);
+            }
+        }
+    }
+
+    /// Tessellates a `begin_shape()`/`end_shape()` block.
+    fn vertex_shape(
+        &mut self,
+        kind: ShapeKind,
+        contours: &[Vec<Vec2>],
+        close: bool,
+        style: &Style,
+        transform: &Affine2,
+    ) {
+        let v = &contours[0];
+        match kind {
+            ShapeKind::Polygon => self.polygon(contours, close, style, transform),
+            ShapeKind::Points => {
+                for &p in v {
+                    self.dot(p, style, transform);
+                }
+            }
+            ShapeKind::Lines => {
+                let line = Style {
+                    fill: None,
+                    ..*style
+                };
+                for pair in v.chunks_exact(2) {
+                    self.shape(pair, false, &line, transform);
+                }
+            }
+            ShapeKind::Triangles => {
+                for tri in v.chunks_exact(3) {
+                    self.shape(tri, true, style, transform);
+                }
+            }
+            ShapeKind::TriangleStrip => {
+                for tri in v.windows(3) {
+                    self.shape(tri, true, style, transform);
+                }
+            }
+            ShapeKind::TriangleFan => {
+                for i in 1..v.len().saturating_sub(1) {
+                    self.shape(&[v[0], v[i], v[i + 1]], true, style, transform);
+                }
+            }
+            ShapeKind::Quads => {
+                for quad in v.chunks_exact(4) {
+                    self.polygon(&[quad.to_vec()], true, style, transform);
+                }
+            }
+            ShapeKind::QuadStrip => {
+                for i in (0..v.len().saturating_sub(3)).step_by(2) {
+                    let quad = vec![v[i], v[i + 1], v[i + 3], v[i + 2]];
+                    self.polygon(&[quad], true, style, transform);
+                }
+            }
+        }
+    }
+
+    /// Replaces the contents of `mesh` with the accumulated triangles.
+    fn write_to(self, mesh: &mut Mesh) {
+        let count = self.positions.len();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; count]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; count]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
+        mesh.insert_indices(Indices::U32(self.indices));
+    }
+}
+
+fn to_world(points: &[Vec2], transform: &Affine2, size: Vec2) -> Vec<Vec2> {
+    points
+        .iter()
+        .map(|&p| canvas_to_world(transform.transform_point2(p), size))
+        .collect()
+}
+
+/// Uniform scale factor of a transform, used to size strokes and curve detail.
+fn transform_scale(transform: &Affine2) -> f32 {
+    transform.matrix2.determinant().abs().sqrt()
+}
+
+/// The style's curve tolerance converted from screen pixels into canvas units.
+fn local_tolerance(style: &Style, transform: &Affine2) -> f32 {
+    style.curve_tolerance / transform_scale(transform)
+}
+
+fn ellipse_points(center: Vec2, w: f32, h: f32, tolerance: f32) -> Vec<Vec2> {
+    let mut points = elliptical_arc(
+        center,
+        Vec2::new(w, h) * 0.5,
+        0.0,
+        std::f32::consts::TAU,
+        tolerance,
+    );
+    // The last point repeats the first.
+    points.pop();
+    points
+}
+
+/// The outline of `bounds` with its corners rounded, clockwise from the top
+/// left. Corners with a radius of 0 stay square.
+fn rounded_rect(bounds: Rect, radii: [f32; 4], tolerance: f32) -> Vec<Vec2> {
+    use std::f32::consts::{FRAC_PI_2, PI};
+    let [tl, tr, br, bl] = radii;
+    let corners = [
+        (Vec2::new(bounds.min.x + tl, bounds.min.y + tl), tl, PI),
+        (
+            Vec2::new(bounds.max.x - tr, bounds.min.y + tr),
+            tr,
+            -FRAC_PI_2,
+        ),
+        (Vec2::new(bounds.max.x - br, bounds.max.y - br), br, 0.0),
+        (
+            Vec2::new(bounds.min.x + bl, bounds.max.y - bl),
+            bl,
+            FRAC_PI_2,
+        ),
+    ];
+    let mut points = Vec::new();
+    for (center, r, start) in corners {
+        if r > 0.0 {
+            let arc = elliptical_arc(center, Vec2::splat(r), start, start + FRAC_PI_2, tolerance);
+            points.extend(arc);
+        } else {
+            points.push(center);
+        }
+    }
+    // Fully rounded sides meet their neighbours at the same point.
+    points.dedup_by(|a, b| a.distance_squared(*b) < 1e-6);
+    if points.len() > 1 && points[0].distance_squared(points[points.len() - 1]) < 1e-6 {
+        points.pop();
+    }
+    points
+}
+
+#[allow(clippy::too_many_arguments)]
+fn arc_outline(
+    center: Vec2,
+    w: f32,
+    h: f32,
+    start: f32,
+    stop: f32,
+    mode: ArcMode,
+    style: &Style,
+    transform: &Affine2,
+    builder: &mut MeshBuilder,
+) {
+    let stop = stop.min(start + std::f32::consts::TAU);
+    let tolerance = local_tolerance(style, transform);
+    let arc = elliptical_arc(center, Vec2::new(w, h) * 0.5, start, stop, tolerance);
+    let mut wedge = arc.clone();
+    if mode == ArcMode::Pie {
+        wedge.push(center);
+    }
+    let fill = Style {
+        stroke: None,
+        ..*style
+    };
+    builder.polygon(&[wedge.clone()], true, &fill, transform);
+    let outline = Style {
+        fill: None,
+        ..*style
+    };
+    match mode {
+        ArcMode::Open => builder.polygon(&[arc], false, &outline, transform),
+        ArcMode::Chord | ArcMode::Pie => builder.polygon(&[wedge], true, &outline, transform),
+    }
+}
+
+/// What `rasterize_frame` reads and writes on each canvas.
+type CanvasFrame = (
+    Entity,
+    &'static mut SketchState,
+    &'static mut SketchMesh,
+    &'static mut TextQueue,
+    &'static mut ImageQueue,
+    Option<&'static RenderLayers>,
+);
+
+fn rasterize_frame(
+    mut commands: Commands,
+    mut canvases: Query<CanvasFrame>,
+    mut batches: Query<(
+        &mut Visibility,
+        &mut Transform,
+        &mut MeshMaterial2d<BlendMaterial>,
+    )>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    materials: Res<BlendMaterials>,
+) {
+    for (canvas, mut state, mut sketch_mesh, mut text_queue, mut image_queue, layers) in
+        &mut canvases
+    {
+        let size = state.surface.size;
+        // A canvas that didn't draw this frame shows its last frame again;
+        // anything its input callbacks drew waits for the next one.
+        let frame = if state.pacing.draws() {
+            record_frame(&mut state);
+            state.shown = std::mem::take(&mut state.commands);
+            state.pixels.forget();
+            state.shown.clone()
+        } else {
+            state.shown.clone()
+        };
+        let builders = tessellate(frame, size, &mut text_queue, &mut image_queue);
+        let count = builders.len();
+        for (i, builder) in builders.into_iter().enumerate() {
+            let (mode, z) = (builder.mode, builder.z);
+            if let Some(batch) = sketch_mesh.batches.get_mut(i) {
+                if let Some(mesh) = meshes.get_mut(&batch.mesh) {
+                    builder.write_to(mesh);
+                }
+                if let Ok((mut visibility, mut transform, mut material)) =
+                    batches.get_mut(batch.entity)
+                {
+                    *visibility = Visibility::Inherited;
+                    transform.translation.z = z;
+                    batch.z = z;
+                    if batch.mode != mode {
+                        material.0 = materials.get(mode);
+                        batch.mode = mode;
+                    }
+                }
+            } else {
+                let mut mesh = Mesh::new(
+                    PrimitiveTopology::TriangleList,
+                    RenderAssetUsages::default(),
+                );
+                builder.write_to(&mut mesh);
+                let mesh = meshes.add(mesh);
+                let entity = commands
+                    .spawn((
+                        Mesh2d(mesh.clone()),
+                        MeshMaterial2d(materials.get(mode)),
+                        Transform::from_xyz(0.0, 0.0, z),
+                        // The bounds change every frame, so skip culling against stale ones.
+                        NoFrustumCulling,
+                        layers.cloned().unwrap_or_default(),
+                        ChildOf(canvas),
+                    ))
+                    .id();
+                sketch_mesh.batches.push(MeshBatch {
+                    entity,
+                    mesh,
+                    mode,
+                    z,
+                });
+            }
+        }
+        // Hide rather than upload an empty vertex buffer.
+        for batch in &sketch_mesh.batches[count..] {
+            if let Ok((mut visibility, ..)) = batches.get_mut(batch.entity) {
+                *visibility = Visibility::Hidden;
+            }
+        }
+        sketch_mesh.used = count;
+    }
+}
+
+/// Turns one canvas's commands into triangles, a batch for each run of shapes
+/// in one blend mode between images and text, queueing those for their own
+/// entities. Everything gets a z from 0 to 1 in painter's order: by `layer()`,
+/// then by call order.
+fn tessellate(
+    mut commands: Vec<ProcessingCommand>,
+    size: Vec2,
+    text_queue: &mut TextQueue,
+    image_queue: &mut ImageQueue,
+) -> Vec<MeshBuilder> {
+    let (texts, images) = (text_queue.0.len(), image_queue.0.len());
+    // Counts batches, images and text from the back, until scaled to z below.
+    let mut depth = 0.0;
+    let mut batches = vec![MeshBuilder { size, ..default() }];
+    let builder = &mut batches[0];
+    // background() paints over everything drawn before it.
+    if let Some(i) = commands
+        .iter()
+        .rposition(|cmd| matches!(cmd, ProcessingCommand::Background { .. }))
+    {
+        if let ProcessingCommand::Background { color } = commands[i] {
+            let corners = [
+                Vec2::ZERO,
+                Vec2::new(size.x, 0.0),
+                size,
+                Vec2::new(0.0, size.y),
+            ];
+            builder.fill(&to_world(&corners, &Affine2::IDENTITY, size), color);
+        }
+        commands.drain(..=i);
+    }
+    // Stable, so each layer keeps its call order.
+    commands.sort_by_key(ProcessingCommand::layer);
+    for cmd in commands {
+        let builder = match cmd.blend_mode() {
+            Some(mode) => batch_for(&mut batches, mode, &mut depth),
+            None => batches.last_mut().unwrap(),
+        };
+        match cmd {
+            ProcessingCommand::Point {
+                x,
+                y,
+                style,
+                transform,
+            } => builder.dot(Vec2::new(x, y), &style, &transform),
+            ProcessingCommand::Line {
+                x1,
+                y1,
+                x2,
+                y2,
+                style,
+                transform,
+            } => {
+                // Lines are never filled, only stroked.
+                let style = Style {
+                    fill: None,
+                    ..style
+                };
+                let points = [Vec2::new(x1, y1), Vec2::new(x2, y2)];
+                builder.shape(&points, false, &style, &transform);
+            }
+            ProcessingCommand::Rect {
+                x,
+                y,
+                w,
+                h,
+                radii,
+                style,
+                transform,
+            } => {
+                let bounds = Rect::new(x, y, x + w, y + h);
+                let tolerance = local_tolerance(&style, &transform);
+                let points = rounded_rect(bounds, radii, tolerance);
+                builder.shape(&points, true, &style, &transform);
+            }
+            ProcessingCommand::Ellipse {
+                cx,
+                cy,
+                w,
+                h,
+                style,
+                transform,
+            } => {
+                let tolerance = local_tolerance(&style, &transform);
+                let points = ellipse_points(Vec2::new(cx, cy), w, h, tolerance);
+                builder.shape(&points, true, &style, &transform);
+            }
+            ProcessingCommand::Arc {
+                cx,
+                cy,
+                w,
+                h,
+                start,
+                stop,
+                mode,
+                style,
+                transform,
+            } => {
+                let center = Vec2::new(cx, cy);
+                arc_outline(center, w, h, start, stop, mode, &style, &transform, builder);
+            }
+            ProcessingCommand::Triangle {
+                x1,
+                y1,
+                x2,
+                y2,
+                x3,
+                y3,
+                style,
+                transform,
+            } => {
+                let points = [Vec2::new(x1, y1), Vec2::new(x2, y2), Vec2::new(x3, y3)];
+                builder.shape(&points, true, &style, &transform);
+            }
+            ProcessingCommand::Quad {
+                corners,
+                style,
+                transform,
+            } => builder.polygon(&[corners.to_vec()], true, &style, &transform),
+            ProcessingCommand::Shape {
+                kind,
+                contours,
+                close,
+                style,
+                transform,
+            } => {
+                builder.vertex_shape(kind, &contours, close, &style, &transform);
+            }
+            ProcessingCommand::Image {
+                image,
+                position,
+                extent,
+                source,
+                mode,
+                tint,
+                transform,
+                layer: _,
+            } => {
+                depth += 1.0;
+                image_queue.0.push(QueuedImage {
+                    image,
+                    position,
+                    extent,
+                    source,
+                    mode,
+                    tint,
+                    transform,
+                    z: depth,
+                });
+            }
+            ProcessingCommand::Text {
+                content,
+                x,
+                y,
+                text_style,
+                style,
+                transform,
+            } => {
+                if let Some(color) = style.fill {
+                    depth += 1.0;
+                    text_queue.0.push(QueuedText {
+                        content,
+                        position: Vec2::new(x, y),
+                        text_style,
+                        color,
+                        transform,
+                        z: depth,
+                    });
+                }
+            }
+            ProcessingCommand::Background { .. } => {}
+        }
+    }
+    batches.retain(|b| !b.indices.is_empty());
+    let slots = depth + 1.0;
+    for batch in &mut batches {
+        batch.z /= slots;
+    }
+    for text in &mut text_queue.0[texts..] {
+        text.z /= slots;
+    }
+    for image in &mut image_queue.0[images..] {
+        image.z /= slots;
+    }
+    batches
+}
+
+/// The batch to draw a shape in `mode` into: the last one, unless it has
+/// shapes in another mode already or an image or text has been queued in
+/// front of it. `depth` is the slot of the frontmost batch, image or text.
+fn batch_for<'a>(
+    batches: &'a mut Vec<MeshBuilder>,
+    mode: BlendMode,
+    depth: &mut f32,
+) -> &'a mut MeshBuilder {
+    let last = batches.last_mut().unwrap();
+    let behind = last.z < *depth;
+    if last.indices.is_empty() {
+        // Nothing to keep in place: bring it to the front.
+        if behind {
+            *depth += 1.0;
+            last.z = *depth;
+        }
+        last.mode = mode;
+    } else if behind || last.mode != mode {
+        *depth += 1.0;
+        let (size, z) = (last.size, *depth);
+        batches.push(MeshBuilder {
+            size,
+            mode,
+            z,
+            ..default()
+        });
+    }
+    batches.last_mut().unwrap()
+}
+
+/// Variables shared between callbacks, like the globals at the top of a Processing sketch.
+struct Globals {
+    brush: f32,
+    hue: f32,
+    font: Option<PFont>,
+    player: Option<PImage>,
+    coin: Option<PImage>,
+    /// What the brush has painted, kept from frame to frame.
+    paint: Option<PGraphics>,
+}
+
+static GLOBALS: Mutex<Globals> = Mutex::new(Globals {
+    brush: 30.0,
+    hue: 200.0,
+    font: None,
+    player: None,
+    coin: None,
+    paint: None,
+});
+
+fn setup() {
+    size(400.0, 400.0);
+    let mut g = GLOBALS.lock().unwrap();
+    g.font = Some(load_font("LinestriderRegular-PjJd 2.ttf"));
+    g.player = Some(load_image(
+        "platformer-art-complete-pack/Base pack/Player/p3_spritesheet.png",
+    ));
+    g.coin = Some(load_image(
+        "platformer-art-complete-pack/Base pack/Items/coinGold.png",
+    ));
+    g.paint = Some(create_graphics(width(), height()));
+    noise_seed(2024);
+    noise_detail(4, 0.5);
+    background(color(26.0, 26.0, 31.0));
+}
+
+fn draw() {
+    background(color(26.0, 26.0, 31.0));
+
+    // Help drawn first, but on a layer of its own over everything else.
+    layer(1);
+    no_stroke();
+    fill(gray_alpha(0.0, 160.0));
+    rect_rounded(4.0, 236.0, 118.0, 68.0, 6.0);
+    fill(gray(255.0));
+    text_font(&PFont::default());
+    text_size(11.0);
+    text_leading(14.0);
+    text_align(TextAlign::Left, TextBaseline::Bottom);
+    text(
+        "drag: paint\nright click: hue\nwheel: brush size\np: pause, n: step",
+        8.0,
+        300.0,
+    );
+    layer(0);
+
+    let t = millis() as f32 / 1000.0;
+    // Stars from a fixed seed, so they land in the same places every frame and run.
+    random_seed(7);
+    for _ in 0..40 {
+        let (x, y) = (random(0.0, width()), random(0.0, height()));
+        let near = dist(x, y, mouse_x(), mouse_y());
+        stroke(gray_alpha(
+            255.0,
+            constrain(map(near, 0.0, 150.0, 255.0, 60.0), 60.0, 255.0),
+        ));
+        stroke_weight(constrain(2.0 + random_gaussian(), 0.5, 4.0));
+        point(x, y);
+    }
+    no_stroke();
+
+    let pulse = lerp(240.0, 360.0, 0.5 + 0.5 * (t * 2.0).sin());
+    let deep = hex_color("#334d99").unwrap();
+    let shallow = hex_color("#3399a6").unwrap();
+    fill(lerp_color(deep, shallow, 0.5 + 0.5 * t.sin()));
+    circle(200.0, 200.0, pulse);
+
+    stroke(Color::WHITE);
+    stroke_weight(3.0);
+    fill(Color::linear_rgb(1.0, 1.0, 0.0));
+    push_matrix();
+    translate(175.0, 150.0);
+    rotate(t * 0.5);
+    rect_mode(ShapeMode::Center);
+    rect_radii(0.0, 0.0, 150.0, 100.0, 30.0, 6.0, 30.0, 6.0);
+    rect_mode(ShapeMode::Corner);
+    pop_matrix();
+    no_fill();
+    stroke(Color::linear_rgb(1.0, 0.0, 1.0));
+    stroke_weight(6.0);
+    ellipse(300.0, 250.0, 150.0, 250.0);
+    fill(Color::linear_rgb(1.0, 0.5, 0.0));
+    stroke(Color::BLACK);
+    stroke_weight(2.0);
+    triangle(100.0, 250.0, 50.0, 350.0, 300.0, 350.0);
+    // An arrowhead: quads may be concave.
+    fill(Color::srgb(0.9, 0.3, 0.35));
+    quad(20.0, 150.0, 60.0, 175.0, 20.0, 200.0, 32.0, 175.0);
+
+    // A five-pointed star with a pentagonal hole punched through it.
+    fill(Color::srgb(0.95, 0.85, 0.2));
+    stroke(Color::BLACK);
+    stroke_weight(2.0);
+    begin_shape(ShapeKind::Polygon);
+    for i in 0..10 {
+        let a = i as f32 * std::f32::consts::PI / 5.0 - std::f32::consts::FRAC_PI_2;
+        let r = if i % 2 == 0 { 45.0 } else { 20.0 };
+        vertex(340.0 + r * a.cos(), 60.0 + r * a.sin());
+    }
+    begin_contour();
+    for i in (0..5).rev() {
+        let a = i as f32 * std::f32::consts::TAU / 5.0 - std::f32::consts::FRAC_PI_2;
+        vertex(340.0 + 10.0 * a.cos(), 60.0 + 10.0 * a.sin());
+    }
+    end_contour();
+    end_shape(EndShape::Close);
+
+    // Red, green and blue lights around the mouse add up to white where they overlap.
+    blend_mode(BlendMode::Add);
+    no_stroke();
+    for i in 0..3 {
+        let a = t + i as f32 * std::f32::consts::TAU / 3.0;
+        let mut channels = [40.0; 3];
+        channels[i] = 255.0;
+        fill(color_alpha(channels[0], channels[1], channels[2], 160.0));
+        ellipse(
+            mouse_x() + 12.0 * a.cos(),
+            mouse_y() + 12.0 * a.sin(),
+            40.0,
+            40.0,
+        );
+    }
+    blend_mode(BlendMode::Blend);
+
+    // A drifting ridge line traced by Perlin noise. Its round joins don't
+    // overlap the segments, so the translucent line is evenly colored.
+    no_fill();
+    stroke(color_alpha(120.0, 140.0, 200.0, 140.0));
+    stroke_weight(5.0);
+    stroke_join(StrokeJoin::Round);
+    begin_shape(ShapeKind::Polygon);
+    for i in 0..=40 {
+        let x = i as f32 * 10.0;
+        vertex(x, map(noise(x * 0.01, t * 0.3, 0.0), 0.0, 1.0, 70.0, 150.0));
+    }
+    end_shape(EndShape::Open);
+    stroke_join(StrokeJoin::Miter);
+
+    // A ribbon built from a triangle strip.
+    fill(Color::srgb(0.3, 0.8, 0.5));
+    stroke_weight(1.0);
+    begin_shape(ShapeKind::TriangleStrip);
+    for i in 0..8 {
+        let x = 20.0 + i as f32 * 20.0;
+        let wave = 8.0 * (t * 3.0 + i as f32 * 0.6).sin();
+        vertex(x, 20.0 + wave);
+        vertex(x, 50.0 + wave);
+    }
+    end_shape(EndShape::Open);
+
+    // A pie chart and a pair of curves along the bottom edge.
+    stroke(Color::WHITE);
+    stroke_weight(1.5);
+    let slices = [0.35, 0.25, 0.4];
+    let mut start = -std::f32::consts::FRAC_PI_2;
+    color_mode_max(ColorMode::Hsb, 360.0, 100.0, 100.0, 1.0);
+    for (i, share) in slices.iter().enumerate() {
+        let stop = start + share * std::f32::consts::TAU;
+        fill(color(i as f32 * 120.0, 60.0, 85.0));
+        arc(360.0, 360.0, 60.0, 60.0, start, stop, ArcMode::Pie);
+        start = stop;
+    }
+    color_mode_max(ColorMode::Rgb, 255.0, 255.0, 255.0, 255.0);
+    no_fill();
+    stroke(Color::srgb(0.4, 0.9, 1.0));
+    stroke_weight(2.0);
+    bezier(
+        20.0,
+        390.0,
+        80.0,
+        300.0 + 40.0 * t.sin(),
+        140.0,
+        400.0,
+        200.0,
+        360.0,
+    );
+    stroke(Color::srgb(1.0, 0.6, 0.8));
+    begin_shape(ShapeKind::Polygon);
+    curve_vertex(20.0, 380.0);
+    for i in 0..6 {
+        curve_vertex(20.0 + i as f32 * 40.0, 380.0 - (i % 2) as f32 * 30.0);
+    }
+    curve_vertex(220.0, 380.0);
+    end_shape(EndShape::Open);
+
+    // A brush that follows the mouse and paints on a layer of its own while dragged.
+    let (brush, hue, paint) = {
+        let g = GLOBALS.lock().unwrap();
+        (g.brush, g.hue, g.paint.clone())
+    };
+    color_mode(ColorMode::Hsb);
+    if let Some(paint) = paint {
+        let ink = color(hue / 360.0 * 255.0, 200.0, 230.0);
+        if mouse_is_pressed() {
+            paint.draw(|| {
+                stroke(ink);
+                stroke_weight(brush * 0.25);
+                line(pmouse_x(), pmouse_y(), mouse_x(), mouse_y());
+            });
+        }
+        image_mode(ImageMode::Corner);
+        image(&paint, 0.0, 0.0);
+    }
+    no_stroke();
+    fill(color_alpha(hue / 360.0 * 255.0, 200.0, 230.0, 128.0));
+    ellipse(mouse_x(), mouse_y(), brush, brush);
+    color_mode(ColorMode::Rgb);
+
+    // A walk cycle cut out of a sprite sheet, and a coin that fades in and out.
+    {
+        let g = GLOBALS.lock().unwrap();
+        if let Some(player) = &g.player {
+            const WALK: [(f32, f32); 6] = [
+                (0.0, 0.0),
+                (73.0, 0.0),
+                (146.0, 0.0),
+                (0.0, 98.0),
+                (73.0, 98.0),
+                (146.0, 98.0),
+            ];
+            let (sx, sy) = WALK[(frame_count() / 6) as usize % WALK.len()];
+            let x = (frame_count() as f32 * 1.5) % 472.0 - 72.0;
+            image_mode(ImageMode::Corner);
+            image_sub(player, x, 200.0, 36.0, 48.5, sx, sy, 72.0, 97.0);
+        }
+        if let Some(coin) = &g.coin {
+            image_mode(ImageMode::Center);
+            tint(Color::srgba(1.0, 1.0, 1.0, 0.5 + 0.5 * (t * 3.0).sin()));
+            image(coin, 40.0, 110.0);
+            no_tint();
+            image_mode(ImageMode::Corners);
+            let (w, h) = (coin.width() * 0.5, coin.height() * 0.5);
+            image_sized(coin, 70.0, 95.0, 70.0 + w, 95.0 + h);
+        }
+    }
+
+    // Labels: a frame counter in the default font and a caption in Linestrider.
+    fill(gray(255.0));
+    text_size(12.0);
+    text_align(TextAlign::Right, TextBaseline::Top);
+    text(format!("frame {}", frame_count()), 392.0, 8.0);
+    if let Some(font) = &GLOBALS.lock().unwrap().font {
+        text_font(font);
+        text_size(20.0);
+        text_align(TextAlign::Center, TextBaseline::Baseline);
+        let caption = "share";
+        text(caption, 360.0, 320.0);
+        let w = text_width(caption);
+        stroke(Color::WHITE);
+        stroke_weight(1.0);
+        line(360.0 - w * 0.5, 323.0, 360.0 + w * 0.5, 323.0);
+    }
+
+    text_font(&PFont::default());
+    text_size(16.0);
+    text_align(TextAlign::Center, TextBaseline::Center);
+    text("processing_like2", 200.0, 200.0);
+
+    // A clock hand that advances one degree per frame, with a ticking second hand at its tip.
+    stroke_cap(StrokeCap::Square);
+    translate(200.0, 200.0);
+    rotate((frame_count() as f32).to_radians());
+    stroke(Color::linear_rgb(1.0, 0.0, 0.0));
+    stroke_weight(4.0);
+    line(0.0, 0.0, 140.0, 0.0);
+    translate(140.0, 0.0);
+    rotate((millis() / 1000) as f32 * std::f32::consts::TAU / 60.0);
+    scale(0.5, 0.5);
+    line(0.0, 0.0, 80.0, 0.0);
+}
+
+fn mouse_pressed() {
+    if mouse_button() == Some(MouseButton::Right) {
+        let mut g = GLOBALS.lock().unwrap();
+        g.hue = (g.hue + 60.0) % 360.0;
+    }
+}
+
+fn mouse_wheel(delta: f32) {
+    let mut g = GLOBALS.lock().unwrap();
+    g.brush = constrain(g.brush - delta * 4.0, 4.0, 120.0);
+}
+
+fn key_pressed() {
+    match key() {
+        Some('+') => mouse_wheel(-1.0),
+        Some('-') => mouse_wheel(1.0),
+        Some('s') => save_frame("processing_like2-####.png"),
+        Some('S') => save("processing_like2.png"),
+        Some('r') => begin_record(RecordFormat::Svg, "processing_like2.svg"),
+        Some('R') => end_record(),
+        Some('f') => full_screen(),
+        Some('p') if is_looping() => no_loop(),
+        Some('p') => r#loop(),
+        Some('n') => redraw(),
+        _ => {}
+    }
+    if key_code() == Some(KeyCode::ArrowRight) {
+        let mut g = GLOBALS.lock().unwrap();
+        g.hue = (g.hue + 15.0) % 360.0;
+    }
+}
+
+/// A second, smaller canvas drawn from an ordinary system through `SketchContext`.
+#[derive(Component)]
+struct Badge;
+
+fn spawn_canvases(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Canvas,
+        WindowCanvas,
+        Sketch {
+            mouse_pressed,
+            mouse_wheel,
+            key_pressed,
+            ..Sketch::new(setup, draw)
+        },
+    ));
+    commands.spawn((
+        Canvas,
+        Badge,
+        Transform::from_xyz(-150.0, -150.0, 2.0).with_scale(Vec3::splat(0.2)),
+    ));
+    // Edit assets/sketches/poster.sketch while this runs to redraw it.
+    commands.spawn((
+        SketchSource(asset_server.load("sketches/poster.sketch")),
+        Transform::from_xyz(150.0, -150.0, 2.0).with_scale(Vec3::splat(0.2)),
+    ));
+    // And assets/sketches/orbits.pjs, a script that restarts when saved or on F5.
+    commands.spawn((
+        ScriptSource(asset_server.load("sketches/orbits.pjs")),
+        Transform::from_xyz(-150.0, 150.0, 2.0).with_scale(Vec3::splat(0.2)),
+    ));
+    commands.spawn((
+        Canvas,
+        Sketch::new(reaction_setup, reaction_draw),
+        Transform::from_xyz(150.0, 150.0, 2.0),
+    ));
+    commands.spawn((
+        Canvas,
+        Sketch::new(garden_setup, garden_draw),
+        Transform::from_xyz(0.0, -150.0, 2.0).with_scale(Vec3::splat(0.5)),
+    ));
+}
+
+/// An L-system and where a turtle starts drawing it on the garden canvas.
+struct Specimen {
+    system: LSystem,
+    start: Vec2,
+    heading: f32,
+    step: f32,
+    color: Color,
+}
+
+/// The specimens the garden canvas grows in turn.
+static GARDEN: Mutex<Vec<Specimen>> = Mutex::new(Vec::new());
+
+/// Frames each specimen takes to grow, and then stays grown for.
+const GROWING: u32 = 180;
+const GROWN: u32 = 60;
+
+fn garden_setup() {
+    size(160.0, 160.0);
+    let specimen = |mut system: LSystem, generation, start, heading, step, color| {
+        system.iterate_to(generation);
+        Specimen {
+            system,
+            start,
+            heading,
+            step,
+            color,
+        }
+    };
+    *GARDEN.lock().unwrap() = vec![
+        specimen(
+            LSystem::plant(),
+            5,
+            Vec2::new(36.0, 150.0),
+            -75.0,
+            1.9,
+            color(120.0, 200.0, 110.0),
+        ),
+        specimen(
+            LSystem::koch(),
+            4,
+            Vec2::new(20.0, 45.0),
+            0.0,
+            120.0 / 81.0,
+            color(150.0, 210.0, 250.0),
+        ),
+        specimen(
+            LSystem::dragon(),
+            10,
+            Vec2::new(64.0, 119.0),
+            0.0,
+            2.9,
+            color(250.0, 150.0, 70.0),
+        ),
+    ];
+}
+
+fn garden_draw() {
+    background(color(20.0, 26.0, 22.0));
+    let garden = GARDEN.lock().unwrap();
+    let period = GROWING + GROWN;
+    let Some(specimen) = garden.get((frame_count() / period) as usize % garden.len().max(1)) else {
+        return;
+    };
+    let mut turtle = Turtle::new(specimen.start.x, specimen.start.y);
+    turtle.set_heading(specimen.heading);
+    turtle.pen_color(specimen.color);
+    turtle.pen_width(1.0);
+    let grown = (frame_count() % period) as f32 / GROWING as f32;
+    let system = &specimen.system;
+    if grown < 1.0 {
+        system.draw_partial(&mut turtle, specimen.step, grown);
+        // The turtle itself, an arrowhead where it has got to.
+        let tip = turtle.position();
+        let mut cursor = Turtle::new(tip.x, tip.y);
+        cursor.set_heading(turtle.heading());
+        cursor.pen_color(Color::WHITE);
+        cursor.pen_width(1.5);
+        for turn in [150.0, 60.0] {
+            cursor.right(turn);
+            cursor.forward(6.0);
+            cursor.back(6.0);
+        }
+    } else {
+        system.draw(&mut turtle, specimen.step);
+    }
+    fill(gray(200.0));
+    text_size(10.0);
+    text_align(TextAlign::Left, TextBaseline::Bottom);
+    text(
+        format!(
+            "generation {}, {} lines",
+            system.generation(),
+            system.segments()
+        ),
+        4.0,
+        158.0,
+    );
+}
+
+/// Amounts of the two chemicals of a Gray-Scott reaction, one pair per pixel.
+static REACTION: Mutex<Vec<Vec2>> = Mutex::new(Vec::new());
+
+fn reaction_setup() {
+    size(80.0, 80.0);
+    // One step of the reaction per draw, several per frame of the window.
+    frame_rate(480.0);
+    let mut cells = REACTION.lock().unwrap();
+    *cells = vec![Vec2::X; 80 * 80];
+    // A few drops of the second chemical to start from.
+    random_seed(3);
+    for _ in 0..6 {
+        let (x, y) = (random(10.0, 70.0) as usize, random(10.0, 70.0) as usize);
+        for i in x - 3..x + 3 {
+            for j in y - 3..y + 3 {
+                cells[j * 80 + i] = Vec2::new(0.5, 0.25);
+            }
+        }
+    }
+}
+
+fn reaction_draw() {
+    let (w, h) = (width() as usize, height() as usize);
+    let mut cells = REACTION.lock().unwrap();
+    if mouse_is_pressed() {
+        let (x, y) = (mouse_x().floor(), mouse_y().floor());
+        if (0.0..width()).contains(&x) && (0.0..height()).contains(&y) {
+            cells[y as usize * w + x as usize] = Vec2::new(0.5, 0.25);
+        }
+    }
+    let (feed, kill) = (0.037, 0.06);
+    let source = cells.clone();
+    for y in 0..h {
+        for x in 0..w {
+            // Wraps around the edges.
+            let at = |dx: usize, dy: usize| source[(y + dy) % h * w + (x + dx) % w];
+            let laplacian =
+                at(w - 1, 0) + at(1, 0) + at(0, h - 1) + at(0, 1) - source[y * w + x] * 4.0;
+            let Vec2 { x: a, y: b } = source[y * w + x];
+            let reaction = a * b * b;
+            cells[y * w + x] = Vec2::new(
+                a + 0.2 * laplacian.x - reaction + feed * (1.0 - a),
+                b + 0.1 * laplacian.y + reaction - (kill + feed) * b,
+            );
+        }
+    }
+    let (low, high) = (color(20.0, 30.0, 60.0), color(250.0, 210.0, 120.0));
+    with_pixels(|pixels| {
+        for (pixel, cell) in pixels.iter_mut().zip(cells.iter()) {
+            *pixel = lerp_color(
+                low,
+                high,
+                constrain((cell.x - cell.y) * 1.5 - 0.3, 0.0, 1.0),
+            );
+        }
+    });
+    update_pixels();
+}
+
+fn draw_badge(mut sketch: SketchContext, badges: Query<Entity, With<Badge>>) {
+    for badge in &badges {
+        sketch.draw(badge, || {
+            background(color(40.0, 40.0, 70.0));
+            let t = millis() as f32 / 1000.0;
+            no_stroke();
+            for i in 0..12 {
+                let a = t + i as f32 * std::f32::consts::TAU / 12.0;
+                fill(gray_alpha(255.0, 40.0 + i as f32 * 18.0));
+                circle(200.0 + 120.0 * a.cos(), 200.0 + 120.0 * a.sin(), 50.0);
+            }
+            // Input is per canvas too: this canvas's mouse is in its own coordinates.
+            let hovered =
+                (0.0..width()).contains(&mouse_x()) && (0.0..height()).contains(&mouse_y());
+            if hovered {
+                no_fill();
+                stroke(Color::WHITE);
+                stroke_weight(20.0);
+                rect_mode(ShapeMode::Corners);
+                rect_rounded(10.0, 10.0, width() - 10.0, height() - 10.0, 40.0);
+            }
+        });
+    }
+}
+
+/// `cargo run --example processing_like2 -- --headless 60 frame.png` draws 60
+/// frames without a window and saves the last one.
+fn main() {
+    let mut app = App::new();
+    match headless_from_args() {
+        Some(headless) => app.add_plugins(headless),
+        None => app.add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                resolution: (DEFAULT_SIZE.x, DEFAULT_SIZE.y).into(),
+                ..default()
+            }),
+            ..default()
+        })),
+    };
+    app.add_plugins(BlendPlugin)
+        .init_asset::<SketchFile>()
+        .init_asset_loader::<SketchFileLoader>()
+        .init_asset::<Script>()
+        .init_asset_loader::<ScriptLoader>()
+        .configure_sets(
+            Update,
+            (SketchSet::Input, SketchSet::Draw, SketchSet::Render).chain(),
+        )
+        .add_systems(Startup, (setup_camera, spawn_canvases))
+        .add_systems(
+            Update,
+            (
+                init_canvases,
+                (sync_font_data, sync_images),
+                run_setup,
+                begin_frame,
+                run_input,
+            )
+                .chain()
+                .in_set(SketchSet::Input),
+        )
+        .add_systems(
+            Update,
+            (run_draw, draw_badge, draw_sketch_files, run_scripts).in_set(SketchSet::Draw),
+        )
+        .add_systems(Update, report_reloads)
+        .add_systems(
+            Update,
+            (
+                spawn_graphics,
+                sync_graphics,
+                upload_pixels,
+                rasterize_frame,
+                save_frames,
+                (update_text, update_images, fit_window),
+            )
+                .chain()
+                .in_set(SketchSet::Render),
+        )
+        .run();
+}
+
+fn setup_camera(mut commands: Commands) {
+    commands.spawn(Camera2d);
+}
+
+/// Marks the canvas that `size()` and `full_screen()` apply to. The camera
+/// frames it at its own aspect ratio, with letterbox bars in the clear color
+/// when the window's aspect ratio differs.
+#[derive(Component)]
+#[require(Canvas)]
+pub struct WindowCanvas;
+
+/// Sizes the window to the `WindowCanvas` and fits the camera to it.
+fn fit_window(
+    canvas: Query<(&SketchState, &GlobalTransform), With<WindowCanvas>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut Camera, &mut Projection, &mut Transform), Without<LayerCamera>>,
+    mut applied: Local<Option<Surface>>,
+) {
+    let (Ok((state, canvas_transform)), Ok(mut window)) = (canvas.single(), windows.single_mut())
+    else {
+        return;
+    };
+    let surface = state.surface;
+    if applied.is_none_or(|a| a.size != surface.size || a.full_screen != surface.full_screen) {
+        if surface.full_screen {
+            window.mode = WindowMode::BorderlessFullscreen(MonitorSelection::Current);
+        } else {
+            window.resolution.set(surface.size.x, surface.size.y);
+        }
+        *applied = Some(surface);
+    }
+
+    let Ok((mut camera, mut projection, mut camera_transform)) = cameras.single_mut() else {
+        return;
+    };
+    let physical = window.physical_size().as_vec2();
+    if physical.min_element() < 1.0 {
+        // Minimized.
+        return;
+    }
+    let scale = (physical / surface.size).min_element();
+    let fitted = (surface.size * scale).round().max(Vec2::ONE);
+    let viewport = Viewport {
+        physical_position: ((physical - fitted) * 0.5).as_uvec2(),
+        physical_size: fitted.as_uvec2(),
+        ..default()
+    };
+    if camera.viewport.as_ref().is_none_or(|v| {
+        v.physical_position != viewport.physical_position
+            || v.physical_size != viewport.physical_size
+    }) {
+        camera.viewport = Some(viewport);
+    }
+
+    let (canvas_scale, _, translation) = canvas_transform.to_scale_rotation_translation();
+    let extent = surface.size * canvas_scale.truncate();
+    if let Projection::Orthographic(ortho) = &mut *projection
+        && !matches!(ortho.scaling_mode, ScalingMode::Fixed { width, height }
+            if Vec2::new(width, height) == extent)
+    {
+        ortho.scaling_mode = ScalingMode::Fixed {
+            width: extent.x,
+            height: extent.y,
+        };
+    }
+    if camera_transform.translation.truncate() != translation.truncate() {
+        camera_transform.translation.x = translation.x;
+        camera_transform.translation.y = translation.y;
+    }
+}
+
+/// Something sketch code can draw on. Its shape mesh, text and images are
+/// children, so moving or scaling the canvas entity moves all of them.
+#[derive(Component, Default)]
+#[require(
+    SketchState,
+    TextQueue,
+    TextPool,
+    ImageQueue,
+    ImagePool,
+    SketchMesh,
+    Transform,
+    Visibility
+)]
+pub struct Canvas;
+
+/// The callbacks of a Processing-style sketch that draws on the `Canvas` it
+/// is attached to. `setup` runs once, before the first `draw`.
+#[derive(Component, Clone, Copy)]
+pub struct Sketch {
+    pub setup: fn(),
+    pub draw: fn(),
+    pub mouse_pressed: fn(),
+    pub mouse_released: fn(),
+    pub mouse_moved: fn(),
+    pub mouse_dragged: fn(),
+    /// Receives the scroll amount, positive towards the user.
+    pub mouse_wheel: fn(f32),
+    pub key_pressed: fn(),
+    pub key_released: fn(),
+}
+
+impl Sketch {
+    /// A sketch that ignores input; set the other callbacks with struct update syntax.
+    pub fn new(setup: fn(), draw: fn()) -> Self {
+        Self {
+            setup,
+            draw,
+            mouse_pressed: || {},
+            mouse_released: || {},
+            mouse_moved: || {},
+            mouse_dragged: || {},
+            mouse_wheel: |_| {},
+            key_pressed: || {},
+            key_released: || {},
+        }
+    }
+}
+
+/// The parts of `Update` where canvases are prepared, drawn on and rendered.
+/// Systems using `SketchContext` belong in `SketchSet::Draw`.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SketchSet {
+    Input,
+    Draw,
+    Render,
+}
+
+/// Lets any system draw on any canvas with the sketch functions, on whichever
+/// thread the executor runs it.
+#[derive(SystemParam)]
+pub struct SketchContext<'w, 's> {
+    canvases: Query<'w, 's, &'static mut SketchState, With<Canvas>>,
+}
+
+impl SketchContext<'_, '_> {
+    /// Runs `f` with `canvas` as the target of every sketch function it calls,
+    /// once for every draw due this frame (see `frame_rate()`). Returns what the
+    /// last run returned, or `None` if `canvas` is not a `Canvas` or doesn't
+    /// draw this frame.
+    pub fn draw<R>(&mut self, canvas: Entity, f: impl FnMut() -> R) -> Option<R> {
+        let mut state = self.canvases.get_mut(canvas).ok()?;
+        run_draws(&mut state, f)
+    }
+
+    /// Runs `f` once with `canvas` as the target of the sketch functions,
+    /// whether or not it draws this frame, as input callbacks are run.
+    fn input<R>(&mut self, canvas: Entity, f: impl FnOnce() -> R) -> Option<R> {
+        let mut state = self.canvases.get_mut(canvas).ok()?;
+        Some(run_user(&mut state, f))
+    }
+
+    /// Whether `canvas` is the one that gets the mouse events.
+    fn under_cursor(&self, canvas: Entity) -> bool {
+        self.canvases
+            .get(canvas)
+            .is_ok_and(|state| state.input.under_cursor)
+    }
+
+    /// Whether `canvas` is the one that gets the key events.
+    fn focused(&self, canvas: Entity) -> bool {
+        self.canvases
+            .get(canvas)
+            .is_ok_and(|state| state.input.focused)
+    }
+}
+
+/// Runs sketch code with `state` installed in the current thread's `STATE`,
+/// which is how the sketch functions it calls find the canvas. Calls may nest,
+/// e.g. a `draw()` that uses a `SketchContext` of its own.
+fn run_user<R>(state: &mut SketchState, f: impl FnOnce() -> R) -> R {
+    STATE.with(|c| std::mem::swap(&mut *c.borrow_mut(), state));
+    let _installed = Installed {
+        state,
+        was_active: ACTIVE.replace(true),
+    };
+    f()
+}
+
+/// Swaps a canvas's state back out of `STATE` when dropped, even if the
+/// sketch code run by `run_user` panics, so the canvas keeps its state and the
+/// thread doesn't keep drawing on it. The state still goes through the
+/// thread-local; this only makes swapping it panic-safe.
+struct Installed<'a> {
+    state: &'a mut SketchState,
+    was_active: bool,
+}
+
+impl Drop for Installed<'_> {
+    fn drop(&mut self) {
+        ACTIVE.set(self.was_active);
+        STATE.with(|c| std::mem::swap(&mut *c.borrow_mut(), self.state));
+    }
+}
+
+/// Gives new canvases the asset server. Their shape meshes are spawned as
+/// `rasterize_frame` needs them.
+fn init_canvases(
+    mut canvases: Query<&mut SketchState, Added<Canvas>>,
+    asset_server: Res<AssetServer>,
+) {
+    for mut state in &mut canvases {
+        state.asset_server = Some(asset_server.clone());
+    }
+}
+
+fn run_setup(mut sketches: Query<(&Sketch, &mut SketchState), Added<Sketch>>) {
+    for (sketch, mut state) in &mut sketches {
+        run_user(&mut state, sketch.setup);
+    }
+}
+
+fn sync_font_data(fonts: Res<Assets<Font>>, mut canvases: Query<&mut SketchState>) {
+    if !fonts.is_changed() {
+        return;
+    }
+    let font_data: HashMap<_, _> = fonts
+        .iter()
+        .map(|(id, font)| (id, font.data.clone()))
+        .collect();
+    for mut state in &mut canvases {
+        state.font_data = font_data.clone();
+    }
+}
+
+/// Starts a new frame on every canvas, before any sketch code draws on it.
+fn begin_frame(time: Res<Time<Real>>, mut canvases: Query<&mut SketchState>) {
+    for mut state in &mut canvases {
+        state.millis = time.elapsed().as_millis() as u32;
+        state.pacing.schedule(time.elapsed_secs_f64());
+        if !state.pacing.draws() {
+            continue;
+        }
+        state.frame_count += 1;
+        // Like Processing, every draw() starts from the identity transform.
+        state.matrix = Affine2::IDENTITY;
+        state.matrix_stack.clear();
+    }
+}
+
+fn run_draw(mut sketches: Query<(&Sketch, &mut SketchState)>) {
+    for (sketch, mut state) in &mut sketches {
+        run_draws(&mut state, sketch.draw);
+    }
+}
+
+/// What `run_input` reads and writes on each canvas.
+type CanvasInput = (
+    Entity,
+    &'static mut SketchState,
+    &'static GlobalTransform,
+    Option<&'static Sketch>,
+    Has<WindowCanvas>,
+);
+
+/// Updates every canvas's input state, in its own coordinates, and fires the
+/// input callbacks, before `draw()`. Mouse events go to the sketch under the
+/// cursor, the topmost one where canvases overlap. Key events go to the last
+/// one clicked, whether or not the cursor is still over it, or to the
+/// `WindowCanvas` until another is clicked.
+#[allow(clippy::too_many_arguments)]
+fn run_input(
+    mut canvases: Query<CanvasInput, With<Canvas>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), Without<LayerCamera>>,
+    held_buttons: Res<ButtonInput<MouseButton>>,
+    held_keys: Res<ButtonInput<KeyCode>>,
+    mut buttons: EventReader<MouseButtonInput>,
+    mut wheel: EventReader<MouseWheel>,
+    mut keys: EventReader<KeyboardInput>,
+) {
+    let buttons: Vec<_> = buttons.read().cloned().collect();
+    let wheel: Vec<_> = wheel.read().cloned().collect();
+    let keys: Vec<_> = keys.read().cloned().collect();
+    let cursor = windows.single().ok().and_then(|w| w.cursor_position());
+    let world = match (cursor, cameras.single()) {
+        (Some(cursor), Ok((camera, camera_transform))) => {
+            camera.viewport_to_world_2d(camera_transform, cursor).ok()
+        }
+        _ => None,
+    };
+
+    let mut under_cursor: Option<(Entity, f32)> = None;
+    for (canvas, mut state, canvas_transform, ..) in &mut canvases {
+        state.input.pmouse = state.input.mouse;
+        let Some(world) = world else {
+            continue;
+        };
+        let local = canvas_transform
+            .affine()
+            .inverse()
+            .transform_point3(world.extend(0.0));
+        let mouse = world_to_canvas(local.truncate(), state.surface.size);
+        state.input.mouse = mouse;
+        let z = canvas_transform.translation().z;
+        if mouse.cmpge(Vec2::ZERO).all()
+            && mouse.cmplt(state.surface.size).all()
+            && under_cursor.is_none_or(|(_, top)| z > top)
+        {
+            under_cursor = Some((canvas, z));
+        }
+    }
+
+    let clicked = buttons.iter().any(|e| e.state == ButtonState::Pressed);
+    let focus = match under_cursor {
+        Some((canvas, _)) if clicked => Some(canvas),
+        _ => canvases
+            .iter()
+            .find(|(_, state, ..)| state.input.focused)
+            .or_else(|| canvases.iter().find(|(.., window)| *window))
+            .map(|(canvas, ..)| canvas),
+    };
+
+    let mouse_pressed = held_buttons.get_pressed().next().is_some();
+    let key_pressed = held_keys.get_pressed().next().is_some();
+    for (canvas, mut state, _, sketch, _) in &mut canvases {
+        let state = &mut *state;
+        state.input.mouse_pressed = mouse_pressed;
+        state.input.key_pressed = key_pressed;
+        state.input.under_cursor = under_cursor.is_some_and(|(top, _)| top == canvas);
+        state.input.focused = focus == Some(canvas);
+        let fire = |state: &mut SketchState, callback: fn(&Sketch) -> fn()| {
+            if let Some(sketch) = sketch {
+                run_user(state, callback(sketch));
+            }
+        };
+        if state.input.under_cursor {
+            for event in &buttons {
+                state.input.mouse_button = Some(event.button);
+                match event.state {
+                    ButtonState::Pressed => fire(state, |s| s.mouse_pressed),
+                    ButtonState::Released => fire(state, |s| s.mouse_released),
+                }
+            }
+
+            if state.input.mouse != state.input.pmouse {
+                if state.input.mouse_pressed {
+                    fire(state, |s| s.mouse_dragged);
+                } else {
+                    fire(state, |s| s.mouse_moved);
+                }
+            }
+
+            for event in &wheel {
+                // Positive when scrolling towards the user, as in Processing.
+                let delta = match event.unit {
+                    MouseScrollUnit::Line => -event.y,
+                    MouseScrollUnit::Pixel => -event.y / 16.0,
+                };
+                if let Some(sketch) = sketch {
+                    run_user(state, || (sketch.mouse_wheel)(delta));
+                }
+            }
+        }
+
+        if !state.input.focused {
+            continue;
+        }
+        for event in &keys {
+            state.input.key_code = Some(event.key_code);
+            state.input.key = match &event.logical_key {
+                Key::Character(character) => character.chars().next(),
+                Key::Space => Some(' '),
+                Key::Enter => Some('\n'),
+                Key::Tab => Some('\t'),
+                Key::Backspace => Some('\u{8}'),
+                Key::Escape => Some('\u{1b}'),
+                Key::Delete => Some('\u{7f}'),
+                _ => None,
+            };
+            match event.state {
+                ButtonState::Pressed => fire(state, |s| s.key_pressed),
+                ButtonState::Released => fire(state, |s| s.key_released),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn a_panicking_sketch_keeps_its_state() {
+        let mut state = SketchState::default();
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            run_user(&mut state, || {
+                point(1.0, 2.0);
+                panic!("the sketch failed");
+            })
+        }));
+
+        assert!(panicked.is_err());
+        // What the sketch drew before panicking went back to its canvas...
+        assert!(matches!(
+            state.commands[..],
+            [ProcessingCommand::Point { x: 1.0, y: 2.0, .. }]
+        ));
+        // ...and the thread is left with nothing installed.
+        assert!(!ACTIVE.get());
+        assert!(STATE.with(|c| c.borrow().commands.is_empty()));
+    }
+
+    #[test]
+    fn arcs_need_stop_past_start() {
+        let mut state = SketchState::default();
+        run_user(&mut state, || {
+            arc(0.0, 0.0, 10.0, 10.0, 1.0, 1.0, ArcMode::Pie);
+            arc(0.0, 0.0, 10.0, 10.0, 2.0, 1.0, ArcMode::Open);
+        });
+        assert!(state.commands.is_empty());
+        run_user(&mut state, || {
+            arc(0.0, 0.0, 10.0, 10.0, 1.0, 2.0, ArcMode::Open);
+        });
+        assert_eq!(state.commands.len(), 1);
+    }
+
+    #[test]
+    fn keys_go_to_the_window_canvas_without_a_cursor() {
+        let mut world = World::new();
+        world.init_resource::<ButtonInput<MouseButton>>();
+        world.init_resource::<ButtonInput<KeyCode>>();
+        world.init_resource::<Events<MouseButtonInput>>();
+        world.init_resource::<Events<MouseWheel>>();
+        world.init_resource::<Events<KeyboardInput>>();
+        let window = world.spawn((WindowCanvas, GlobalTransform::default())).id();
+        let other = world.spawn((Canvas, GlobalTransform::default())).id();
+        world.send_event(KeyboardInput {
+            key_code: KeyCode::KeyP,
+            logical_key: Key::Character("p".into()),
+            state: ButtonState::Pressed,
+            text: None,
+            repeat: false,
+            window: Entity::PLACEHOLDER,
+        });
+        world.run_system_once(run_input).unwrap();
+
+        let key = |canvas| world.get::<SketchState>(canvas).unwrap().input.key;
+        assert_eq!(key(window), Some('p'));
+        assert_eq!(key(other), None);
+    }
+}
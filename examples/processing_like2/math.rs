@@ -0,0 +1,170 @@
+//! `random()`, `noise()` and the small numeric helpers sketches lean on. Both
+//! generators are seeded, so `random_seed()` and `noise_seed()` make a sketch
+//! draw exactly the same picture on every run.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use super::with_state;
+
+/// The sketch's random generator and noise field.
+#[derive(Clone)]
+pub(super) struct RandomState {
+    rng: StdRng,
+    noise: Noise,
+}
+
+impl Default for RandomState {
+    /// Unseeded sketches differ from run to run, as in Processing.
+    fn default() -> Self {
+        let mut rng = StdRng::from_os_rng();
+        let noise = Noise::new(rng.random());
+        Self { rng, noise }
+    }
+}
+
+/// Fractal 3D Perlin noise: `octaves` layers of gradient noise, each at twice
+/// the frequency and `falloff` times the amplitude of the one before.
+#[derive(Clone)]
+struct Noise {
+    /// A shuffled 0..256 written out twice so lookups never need to wrap.
+    permutation: Vec<u8>,
+    octaves: u32,
+    falloff: f32,
+}
+
+impl Noise {
+    fn new(seed: u64) -> Self {
+        let mut table: Vec<u8> = (0..=255).collect();
+        table.shuffle(&mut StdRng::seed_from_u64(seed));
+        let permutation = table.iter().chain(&table).copied().collect();
+        Self {
+            permutation,
+            octaves: 4,
+            falloff: 0.5,
+        }
+    }
+
+    fn sample(&self, x: f32, y: f32, z: f32) -> f32 {
+        let (mut sum, mut amplitude, mut frequency, mut total) = (0.0, 1.0, 1.0, 0.0);
+        for _ in 0..self.octaves.max(1) {
+            sum += self.perlin(x * frequency, y * frequency, z * frequency) * amplitude;
+            total += amplitude;
+            amplitude *= self.falloff;
+            frequency *= 2.0;
+        }
+        // Perlin noise lies in -1..=1; Processing's noise() is 0..=1.
+        (sum / total * 0.5 + 0.5).clamp(0.0, 1.0)
+    }
+
+    /// Ken Perlin's improved noise.
+    fn perlin(&self, x: f32, y: f32, z: f32) -> f32 {
+        let p = &self.permutation;
+        let cell = |v: f32| (v.floor() as i32 & 255) as usize;
+        let (xi, yi, zi) = (cell(x), cell(y), cell(z));
+        let (x, y, z) = (x - x.floor(), y - y.floor(), z - z.floor());
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let a = p[xi] as usize + yi;
+        let (aa, ab) = (p[a] as usize + zi, p[a + 1] as usize + zi);
+        let b = p[xi + 1] as usize + yi;
+        let (ba, bb) = (p[b] as usize + zi, p[b + 1] as usize + zi);
+
+        let lerp = |t: f32, a: f32, b: f32| a + t * (b - a);
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, grad(p[aa], x, y, z), grad(p[ba], x - 1.0, y, z)),
+                lerp(
+                    u,
+                    grad(p[ab], x, y - 1.0, z),
+                    grad(p[bb], x - 1.0, y - 1.0, z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(p[aa + 1], x, y, z - 1.0),
+                    grad(p[ba + 1], x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    grad(p[ab + 1], x, y - 1.0, z - 1.0),
+                    grad(p[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+/// Dot product of `(x, y, z)` with one of 12 cube-edge gradients picked by `hash`.
+fn grad(hash: u8, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = match h {
+        0..4 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// Restarts `random()` and `random_gaussian()` from `seed`.
+pub fn random_seed(seed: u64) {
+    with_state(|s| s.random.rng = StdRng::seed_from_u64(seed));
+}
+/// A uniformly distributed number in `lo..hi`, or `lo` if the range is empty.
+pub fn random(lo: f32, hi: f32) -> f32 {
+    if hi <= lo {
+        return lo;
+    }
+    with_state(|s| s.random.rng.random_range(lo..hi))
+}
+/// A normally distributed number with mean 0 and standard deviation 1.
+pub fn random_gaussian() -> f32 {
+    with_state(|s| {
+        // Box-Muller; `1 - u` keeps the logarithm away from zero.
+        let u: f32 = 1.0 - s.random.rng.random::<f32>();
+        let v: f32 = s.random.rng.random();
+        (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
+    })
+}
+
+/// Perlin noise at `(x, y, z)`, in 0..=1. Pass 0 for unused dimensions; nearby
+/// inputs give nearby outputs, so step by about 0.005 to 0.03 for smooth motion.
+pub fn noise(x: f32, y: f32, z: f32) -> f32 {
+    with_state(|s| s.random.noise.sample(x, y, z))
+}
+/// Regenerates the noise field from `seed`, keeping the current detail.
+pub fn noise_seed(seed: u64) {
+    with_state(|s| s.random.noise.permutation = Noise::new(seed).permutation);
+}
+/// Sets how many octaves `noise()` sums and how much each is weaker than the
+/// last; Processing's default is 4 and 0.5.
+pub fn noise_detail(octaves: u32, falloff: f32) {
+    with_state(|s| {
+        s.random.noise.octaves = octaves;
+        s.random.noise.falloff = falloff;
+    });
+}
+
+/// Re-maps `value` from the range `start1..stop1` to `start2..stop2`.
+pub fn map(value: f32, start1: f32, stop1: f32, start2: f32, stop2: f32) -> f32 {
+    start2 + (stop2 - start2) * ((value - start1) / (stop1 - start1))
+}
+pub fn constrain(value: f32, low: f32, high: f32) -> f32 {
+    value.max(low).min(high)
+}
+pub fn lerp(start: f32, stop: f32, amount: f32) -> f32 {
+    start + (stop - start) * amount
+}
+pub fn dist(x1: f32, y1: f32, x2: f32, y2: f32) -> f32 {
+    (x2 - x1).hypot(y2 - y1)
+}
@@ -0,0 +1,176 @@
+//! `frame_rate()`, `no_loop()`, `r#loop()` and `redraw()`: how often a canvas's
+//! `draw()` runs, independently of how often the window is rendered. A canvas
+//! that isn't drawn on a frame shows what it drew last.
+
+use bevy::math::Affine2;
+
+use super::svg::rewind_recording;
+use super::{SketchState, run_user, with_state};
+
+/// Draws a frame may run to catch up with a `frame_rate()` above the render
+/// rate. A canvas further behind than that skips the draws it missed.
+const MAX_DRAWS_PER_FRAME: u32 = 16;
+
+/// When a canvas's `draw()` is due.
+#[derive(Clone)]
+pub(super) struct Pacing {
+    /// Draws per second, or `None` to draw once every frame.
+    frame_rate: Option<f32>,
+    looping: bool,
+    /// Set by `redraw()` until the next frame draws once.
+    redraw: bool,
+    /// When the next draw at `frame_rate` is due, in seconds since startup.
+    next: Option<f64>,
+    /// When the last frame that drew started.
+    last: Option<f64>,
+    /// Times `draw()` runs this frame.
+    draws: u32,
+    /// Milliseconds from one draw to the next, for `delta_time()`.
+    delta: f32,
+}
+
+impl Default for Pacing {
+    fn default() -> Self {
+        Self {
+            frame_rate: None,
+            looping: true,
+            redraw: false,
+            next: None,
+            last: None,
+            draws: 0,
+            delta: 0.0,
+        }
+    }
+}
+
+impl Pacing {
+    /// Works out how many draws are due on the frame starting at `now` seconds.
+    pub(super) fn schedule(&mut self, now: f64) {
+        self.draws = match (self.looping, self.frame_rate) {
+            // Like Processing, a sketch that stops in `setup()` still draws once.
+            (false, _) => u32::from(self.redraw || self.last.is_none()),
+            (true, None) => 1,
+            (true, Some(rate)) => {
+                let interval = 1.0 / rate as f64;
+                let next = *self.next.get_or_insert(now);
+                if next > now {
+                    0
+                } else {
+                    let due = ((now - next) / interval) as u32 + 1;
+                    self.next = Some(if due > MAX_DRAWS_PER_FRAME {
+                        now + interval
+                    } else {
+                        next + due as f64 * interval
+                    });
+                    due.min(MAX_DRAWS_PER_FRAME)
+                }
+            }
+        };
+        self.redraw = false;
+        if self.draws > 0 {
+            let elapsed = now - self.last.unwrap_or(now);
+            self.delta = (elapsed * 1000.0) as f32 / self.draws as f32;
+            self.last = Some(now);
+        }
+    }
+
+    /// Whether the canvas draws this frame, rather than showing its last frame again.
+    pub(super) fn draws(&self) -> bool {
+        self.draws > 0
+    }
+}
+
+/// Runs `f` once for every draw due on `state` this frame, and returns what
+/// the last run did. Only the last run's drawing is shown; the earlier ones
+/// just advance the sketch, e.g. a simulation running faster than the window.
+pub(super) fn run_draws<R>(state: &mut SketchState, mut f: impl FnMut() -> R) -> Option<R> {
+    // What `setup()` and the input callbacks drew this frame stays.
+    let kept = state.commands.len();
+    let mut result = None;
+    for i in 0..state.pacing.draws {
+        // `begin_frame` has started the first.
+        if i > 0 {
+            state.commands.truncate(kept);
+            rewind_recording(state, kept);
+            state.pixels.forget();
+            state.frame_count += 1;
+            state.matrix = Affine2::IDENTITY;
+            state.matrix_stack.clear();
+        }
+        result = Some(run_user(state, &mut f));
+    }
+    result
+}
+
+/// Runs `draw()` `fps` times a second instead of once every frame, as long as
+/// the sketch keeps up. Values of zero or less are ignored.
+pub fn frame_rate(fps: f32) {
+    with_state(|s| {
+        // Called every draw, the schedule carries on rather than restarting.
+        if fps > 0.0 && s.pacing.frame_rate != Some(fps) {
+            s.pacing.frame_rate = Some(fps);
+            s.pacing.next = None;
+        }
+    });
+}
+
+/// Stops running `draw()`, leaving the last frame on the canvas. Input
+/// callbacks still run, and may call `redraw()` or `r#loop()`.
+pub fn no_loop() {
+    with_state(|s| s.pacing.looping = false);
+}
+
+/// Starts running `draw()` again after `no_loop()`.
+pub fn r#loop() {
+    with_state(|s| {
+        s.pacing.looping = true;
+        // No catching up on the draws missed while stopped.
+        s.pacing.next = None;
+    });
+}
+
+/// Whether `draw()` runs, i.e. `no_loop()` hasn't been called since `r#loop()`.
+pub fn is_looping() -> bool {
+    with_state(|s| s.pacing.looping)
+}
+
+/// Runs `draw()` once on the next frame after `no_loop()`.
+pub fn redraw() {
+    with_state(|s| s.pacing.redraw = true);
+}
+
+/// Milliseconds from the previous `draw()` to this one.
+pub fn delta_time() -> f32 {
+    with_state(|s| s.pacing.delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ProcessingCommand, point};
+    use super::*;
+
+    #[test]
+    fn catching_up_keeps_what_setup_drew() {
+        let mut state = SketchState::default();
+        run_user(&mut state, || point(1.0, 1.0));
+        state.pacing.draws = 2;
+        let mut runs = 0;
+        run_draws(&mut state, || {
+            runs += 1;
+            point(runs as f32, 5.0);
+        });
+
+        assert_eq!(runs, 2);
+        let points: Vec<_> = state
+            .commands
+            .iter()
+            .map(|cmd| match cmd {
+                ProcessingCommand::Point { x, y, .. } => (*x, *y),
+                _ => panic!("unexpected command"),
+            })
+            .collect();
+        // The setup's point, then only the last draw's.
+        assert_eq!(points, [(1.0, 1.0), (2.0, 5.0)]);
+        assert_eq!(state.frame_count, 1);
+    }
+}
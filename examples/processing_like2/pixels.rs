@@ -0,0 +1,397 @@
+//! `pixels`: the canvas's pixels, one color each, with Processing's
+//! `load_pixels()`, `update_pixels()`, `get()`, `set()` and `filter()`.
+//!
+//! The canvas is drawn on the GPU after `draw()` returns, so `load_pixels()`
+//! draws what the canvas holds so far on the CPU instead, with the renderer
+//! behind `save()`. `update_pixels()` then makes the pixels the canvas: what
+//! was drawn on it is replaced by an `Image` holding them, which everything
+//! drawn afterwards goes over.
+
+use std::sync::Arc;
+
+use bevy::image::ImageSampler;
+use bevy::math::Affine2;
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+use super::graphics::{GraphicsLayer, layer_state};
+use super::image::ImageMode;
+use super::software::{FrameAssets, render_pixels};
+use super::{ProcessingCommand, SketchState, with_state};
+
+/// What `filter()` does to each pixel.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Filter {
+    /// Replaces each color with its luminance.
+    Gray,
+    /// Inverts red, green and blue.
+    Invert,
+    /// Turns pixels at least this bright (0 to 1) white and the others black.
+    Threshold(f32),
+    /// A Gaussian blur with this radius in pixels.
+    Blur(f32),
+}
+
+/// A canvas's `pixels`, and the image that shows them once `update_pixels()`
+/// has made them the canvas.
+#[derive(Clone, Default)]
+pub(super) struct Pixels {
+    width: usize,
+    height: usize,
+    /// Row by row from the top-left corner.
+    colors: Vec<Color>,
+    image: Option<Handle<Image>>,
+    /// Set by `update_pixels()` until `image` holds `colors`.
+    dirty: bool,
+    /// Set by `update_pixels()` until the state's copy of `image` holds
+    /// `colors` for `load_pixels()`.
+    stale: bool,
+    /// How many of the canvas's commands `colors` shows, while nothing else
+    /// has been drawn or the commands cut back since.
+    shows: Option<usize>,
+}
+
+impl Pixels {
+    /// Matches the buffer to the canvas size, clearing it if that changed.
+    fn fit(&mut self, size: Vec2) {
+        let (width, height) = (size.x as usize, size.y as usize);
+        if (width, height) != (self.width, self.height) || self.colors.is_empty() {
+            self.width = width;
+            self.height = height;
+            self.colors = vec![Color::NONE; width * height];
+            self.shows = None;
+        }
+    }
+
+    fn index(&self, x: f32, y: f32) -> Option<usize> {
+        let (x, y) = (x.floor(), y.floor());
+        let inside =
+            x >= 0.0 && y >= 0.0 && (x as usize) < self.width && (y as usize) < self.height;
+        inside.then(|| y as usize * self.width + x as usize)
+    }
+
+    /// Notes that the canvas's commands were taken or cut back, so the
+    /// buffer may no longer show them.
+    pub(super) fn forget(&mut self) {
+        self.shows = None;
+    }
+
+    /// `colors` as an image the size of the canvas, for the GPU and for
+    /// `load_pixels()` to draw with.
+    fn to_image(&self) -> Image {
+        let mut image = Image::new(
+            Extent3d {
+                width: self.width as u32,
+                height: self.height as u32,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            self.colors
+                .iter()
+                .flat_map(|c| c.to_srgba().to_u8_array())
+                .collect(),
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        // Scaled up, each pixel stays a crisp square.
+        image.sampler = ImageSampler::nearest();
+        image
+    }
+}
+
+/// What `load_pixels()` draws images and text with: the copies of loaded
+/// images and fonts in the canvas's state.
+impl FrameAssets for SketchState {
+    fn image(&self, id: AssetId<Image>) -> Option<&Image> {
+        self.images.get(&id).map(|image| &**image)
+    }
+    fn font(&self, id: AssetId<Font>) -> Option<&[u8]> {
+        self.font_data.get(&id).map(|data| &data[..])
+    }
+}
+
+/// Fills `pixels` with what has been drawn on the canvas so far in this
+/// `draw()`, or since the last one from `setup()` and the input callbacks.
+/// Areas nothing was drawn on are transparent. On a `create_graphics()`
+/// layer that is only what was drawn on it since the last frame.
+pub fn load_pixels() {
+    with_state(load);
+}
+
+fn load(s: &mut SketchState) {
+    let size = s.surface.size;
+    s.pixels.fit(size);
+    // The commands may draw `image` from an earlier `update_pixels()`.
+    if let Some(handle) = s.pixels.image.as_ref().filter(|_| s.pixels.stale) {
+        s.images.insert(handle.id(), Arc::new(s.pixels.to_image()));
+        s.pixels.stale = false;
+    }
+    s.pixels.colors = render_pixels(s.commands.clone(), size, &*s);
+    s.pixels.shows = Some(s.commands.len());
+}
+
+/// Makes `pixels` the canvas: everything drawn on it so far is replaced by
+/// the pixels, and what is drawn afterwards goes over them. Call
+/// `load_pixels()` first to start from what was drawn.
+pub fn update_pixels() {
+    with_state(update);
+}
+
+fn update(s: &mut SketchState) {
+    let size = s.surface.size;
+    let pixels = &mut s.pixels;
+    pixels.fit(size);
+    pixels.dirty = true;
+    pixels.stale = true;
+    if pixels.image.is_none() {
+        let image = pixels.to_image();
+        pixels.image = Some(
+            s.asset_server
+                .as_ref()
+                .map(|server| server.add(image))
+                .unwrap_or_default(),
+        );
+    }
+    let image = pixels.image.clone().unwrap_or_default();
+    s.commands = vec![ProcessingCommand::Image {
+        image,
+        position: Vec2::ZERO,
+        extent: Some(size),
+        source: None,
+        mode: ImageMode::Corner,
+        tint: Color::WHITE,
+        transform: Affine2::IDENTITY,
+        // Under everything drawn afterwards, whatever its `layer()`.
+        layer: i32::MIN,
+    }];
+    s.pixels.shows = Some(s.commands.len());
+}
+
+/// Loads the canvas into `pixels`, unless they show it already.
+fn load_if_needed(s: &mut SketchState) {
+    if s.pixels.shows != Some(s.commands.len()) || s.pixels.colors.is_empty() {
+        load(s);
+    }
+}
+
+/// The color of the canvas's pixel at `(x, y)`, transparent outside the
+/// canvas. Like `load_pixels()`, that includes everything drawn so far.
+pub fn get(x: f32, y: f32) -> Color {
+    with_state(|s| {
+        load_if_needed(s);
+        match s.pixels.index(x, y) {
+            Some(i) => s.pixels.colors[i],
+            None => Color::NONE,
+        }
+    })
+}
+
+/// Sets the canvas's pixel at `(x, y)`, drawing over whatever is there.
+pub fn set(x: f32, y: f32, color: Color) {
+    with_state(|s| {
+        load_if_needed(s);
+        if let Some(i) = s.pixels.index(x, y) {
+            s.pixels.colors[i] = color;
+            update(s);
+        }
+    });
+}
+
+/// Runs `f` on `pixels`, row by row from the top-left corner, `width()` to a
+/// row. Much faster than `set()` for changing every pixel; call
+/// `load_pixels()` before to see what's drawn, and `update_pixels()` after to
+/// show the changes.
+pub fn with_pixels<R>(f: impl FnOnce(&mut [Color]) -> R) -> R {
+    // Taken out of the state, so that `f` may call other sketch functions.
+    let mut colors = with_state(|s| {
+        let size = s.surface.size;
+        s.pixels.fit(size);
+        std::mem::take(&mut s.pixels.colors)
+    });
+    let result = f(&mut colors);
+    with_state(|s| s.pixels.colors = colors);
+    result
+}
+
+/// Applies `kind` to every pixel of the canvas.
+pub fn filter(kind: Filter) {
+    with_state(|s| {
+        load_if_needed(s);
+        let (colors, width) = (&mut s.pixels.colors, s.pixels.width);
+        match kind {
+            Filter::Gray => {
+                for c in colors.iter_mut() {
+                    let l = luminance(*c);
+                    *c = Color::srgba(l, l, l, c.alpha());
+                }
+            }
+            Filter::Invert => {
+                for c in colors.iter_mut() {
+                    let s = c.to_srgba();
+                    *c = Color::srgba(1.0 - s.red, 1.0 - s.green, 1.0 - s.blue, s.alpha);
+                }
+            }
+            Filter::Threshold(level) => {
+                for c in colors.iter_mut() {
+                    let v = if luminance(*c) >= level { 1.0 } else { 0.0 };
+                    *c = Color::srgba(v, v, v, c.alpha());
+                }
+            }
+            Filter::Blur(radius) => blur(colors, width, radius),
+        }
+        update(s);
+    });
+}
+
+/// Perceived brightness of an sRGB color, weighted as Processing's `GRAY` does.
+fn luminance(c: Color) -> f32 {
+    let s = c.to_srgba();
+    0.3 * s.red + 0.59 * s.green + 0.11 * s.blue
+}
+
+/// Blurs rows then columns with a Gaussian kernel of standard deviation
+/// `radius / 2`, clamping at the edges. Colors are premultiplied while mixed,
+/// so transparent pixels don't bleed black.
+fn blur(colors: &mut [Color], width: usize, radius: f32) {
+    if width == 0 || radius <= 0.0 {
+        return;
+    }
+    let height = colors.len() / width;
+    let reach = radius.ceil() as i32;
+    let sigma = (radius * 0.5).max(0.5);
+    let kernel: Vec<f32> = (-reach..=reach)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = kernel.iter().sum();
+
+    let mut values: Vec<Vec4> = colors
+        .iter()
+        .map(|c| {
+            let s = c.to_srgba();
+            Vec4::new(
+                s.red * s.alpha,
+                s.green * s.alpha,
+                s.blue * s.alpha,
+                s.alpha,
+            )
+        })
+        .collect();
+    // Along each row, then down each column: `stride` apart within a line,
+    // `next` apart from one line to the next.
+    for (len, lines, stride, next) in [(width, height, 1, width), (height, width, width, 1)] {
+        let source = values.clone();
+        for line in 0..lines {
+            for i in 0..len {
+                let mut sum = Vec4::ZERO;
+                for (k, weight) in kernel.iter().enumerate() {
+                    let j = (i as i32 + k as i32 - reach).clamp(0, len as i32 - 1) as usize;
+                    sum += source[line * next + j * stride] * *weight;
+                }
+                values[line * next + i * stride] = sum / total;
+            }
+        }
+    }
+
+    for (c, v) in colors.iter_mut().zip(values) {
+        *c = if v.w > 0.0 {
+            Color::srgba(v.x / v.w, v.y / v.w, v.z / v.w, v.w)
+        } else {
+            Color::NONE
+        };
+    }
+}
+
+/// Copies each canvas's `pixels` into its image after `update_pixels()`.
+pub(super) fn upload_pixels(
+    mut canvases: Query<&mut SketchState, Without<GraphicsLayer>>,
+    layers: Query<&GraphicsLayer>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for mut state in &mut canvases {
+        upload(&mut state.pixels, &mut images);
+    }
+    // A layer's pixels stay in the state its `draw()` calls write to.
+    for layer in &layers {
+        upload(&mut layer_state(layer).lock().unwrap().pixels, &mut images);
+    }
+}
+
+fn upload(pixels: &mut Pixels, images: &mut Assets<Image>) {
+    if !pixels.dirty {
+        return;
+    }
+    // The image appears in `images` the frame after `update_pixels()` adds it.
+    let Some(image) = pixels.image.as_ref().and_then(|h| images.get_mut(h)) else {
+        return;
+    };
+    let size = Extent3d {
+        width: pixels.width as u32,
+        height: pixels.height as u32,
+        depth_or_array_layers: 1,
+    };
+    if image.texture_descriptor.size != size {
+        image.resize(size);
+    }
+    image.data = Some(
+        pixels
+            .colors
+            .iter()
+            .flat_map(|c| c.to_srgba().to_u8_array())
+            .collect(),
+    );
+    pixels.dirty = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{fill, no_stroke, rect, run_user};
+    use super::*;
+
+    fn near(a: Color, b: Color) -> bool {
+        let (a, b) = (a.to_srgba(), b.to_srgba());
+        (a.red - b.red).abs() < 0.02
+            && (a.green - b.green).abs() < 0.02
+            && (a.blue - b.blue).abs() < 0.02
+            && (a.alpha - b.alpha).abs() < 0.02
+    }
+
+    #[test]
+    fn pixels_are_the_canvas() {
+        let mut state = SketchState::default();
+        state.surface.size = Vec2::new(20.0, 10.0);
+        let (red, green, blue) = (
+            Color::srgb(1.0, 0.0, 0.0),
+            Color::srgb(0.0, 1.0, 0.0),
+            Color::srgb(0.0, 0.0, 1.0),
+        );
+        run_user(&mut state, || {
+            no_stroke();
+            fill(red);
+            rect(0.0, 0.0, 10.0, 10.0);
+            load_pixels();
+            assert!(near(get(5.0, 5.0), red));
+            assert!(near(get(15.0, 5.0), Color::NONE));
+            assert!(near(get(25.0, 5.0), Color::NONE));
+
+            set(15.0, 5.0, blue);
+            assert!(near(get(15.0, 5.0), blue));
+        });
+        // What was drawn is now the pixels.
+        assert!(matches!(
+            &state.commands[..],
+            [ProcessingCommand::Image {
+                layer: i32::MIN,
+                ..
+            }]
+        ));
+        run_user(&mut state, || {
+            // Drawn over the pixels, and loaded again to be read.
+            fill(green);
+            rect(10.0, 0.0, 10.0, 5.0);
+            assert!(near(get(15.0, 2.0), green));
+            assert!(near(get(15.0, 5.0), blue));
+            assert!(near(get(5.0, 5.0), red));
+        });
+    }
+}
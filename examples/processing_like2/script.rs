@@ -0,0 +1,1165 @@
+//! A small interpreter for sketches written in a Processing/JavaScript-like
+//! language, so sketch logic can change while the example runs:
+//!
+//! ```text
+//! let angle = 0;
+//!
+//! function setup() {
+//!   colorMode(HSB, 360, 100, 100);
+//! }
+//!
+//! void draw() {
+//!   background(20);
+//!   for (let i = 0; i < 12; i++) {
+//!     fill(i * 30, 80, 100);
+//!     ellipse(200 + 150 * cos(angle + i), 200 + 150 * sin(angle + i), 20, 20);
+//!   }
+//!   angle += 0.02;
+//! }
+//! ```
+//!
+//! Scripts have numbers, strings, booleans, colors, arrays and `null`;
+//! `let`/`var`/`const` and Processing's type names all declare a variable,
+//! without checking its type. The sketch functions are there under their
+//! Processing names (`strokeWeight`, `beginShape`, ...) together with `mouseX`,
+//! `frameCount`, `width` and the other usual variables, and `setup()`,
+//! `draw()`, `mousePressed()`, `mouseReleased()`, `mouseMoved()`,
+//! `mouseDragged()`, `keyPressed()` and `keyReleased()` are called like the
+//! callbacks of a `Sketch`.
+//!
+//! A script restarts from the top whenever its file changes or F5 is pressed.
+//! Errors are logged with their line and column and shown on the canvas.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::input::ButtonState;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::mouse::MouseButtonInput;
+use bevy::prelude::*;
+
+mod builtins;
+mod parse;
+
+use super::color::color;
+use super::pacing::redraw;
+use super::text::{TextAlign, TextBaseline, text, text_align, text_size};
+use super::{
+    SketchContext, background, fill, is_under_cursor, mouse_is_pressed, mouse_x, mouse_y, pmouse_x,
+    pmouse_y, reset_matrix,
+};
+use parse::{BinaryOp, Expr, ExprKind, Function, Pos, Program, Stmt};
+
+/// Loop iterations and calls one callback may make before it's assumed to be stuck.
+const STEP_LIMIT: u32 = 10_000_000;
+/// How deeply script functions may call each other.
+const DEPTH_LIMIT: u32 = 100;
+/// How deeply expressions may nest while running, counting every call they
+/// are inside, before the interpreter could overflow the stack.
+const NESTING_LIMIT: u32 = 500;
+
+/// A loaded script file. One that doesn't parse still loads, so the canvas
+/// can show what's wrong with it.
+#[derive(Asset, TypePath)]
+pub struct Script {
+    name: String,
+    source: String,
+    program: Result<Arc<Program>, ScriptError>,
+}
+
+/// Runs a `Script` on the `Canvas` it is attached to.
+#[derive(Component, Clone)]
+#[require(super::Canvas, ScriptRuntime)]
+pub struct ScriptSource(pub Handle<Script>);
+
+/// A problem in a script, at the place it was found.
+#[derive(Clone, Debug)]
+pub struct ScriptError {
+    pos: Pos,
+    message: String,
+}
+
+impl ScriptError {
+    fn new(pos: Pos, message: impl Into<String>) -> Self {
+        Self {
+            pos,
+            message: message.into(),
+        }
+    }
+
+    /// The error followed by the line it is on, with a caret under its column.
+    fn report(&self, name: &str, source: &str) -> String {
+        let Pos { line, column } = self.pos;
+        // Errors outside any line, like a stuck `draw()`, have no position.
+        if line == 0 {
+            return format!("{name}: {}", self.message);
+        }
+        let mut report = format!("{name}:{line}:{column}: {}", self.message);
+        if let Some(text) = source.lines().nth(line as usize - 1) {
+            // Tabs would throw the caret off; show them as single spaces.
+            let text = text.replace('\t', " ");
+            let indent = " ".repeat(column as usize - 1);
+            report += &format!("\n{text}\n{indent}^");
+        }
+        report
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Pos { line, column } = self.pos;
+        write!(f, "line {line}, column {column}: {}", self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// A value in a running script. Arrays are shared, as in JavaScript.
+#[derive(Clone)]
+enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(Arc<str>),
+    Color(Color),
+    Array(Arc<Mutex<Vec<Value>>>),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "a boolean",
+            Value::Number(_) => "a number",
+            Value::Str(_) => "a string",
+            Value::Color(_) => "a color",
+            Value::Array(_) => "an array",
+        }
+    }
+
+    fn is_truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(b) => *b,
+            Value::Number(n) => *n != 0.0 && !n.is_nan(),
+            Value::Str(s) => !s.is_empty(),
+            Value::Color(_) | Value::Array(_) => true,
+        }
+    }
+
+    fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Color(a), Value::Color(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// Writes the value, with `[...]` in place of an array that contains
+    /// itself. `open` holds the arrays being written, outermost first.
+    fn write(
+        &self,
+        f: &mut fmt::Formatter,
+        open: &mut Vec<*const Mutex<Vec<Value>>>,
+    ) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{b}"),
+            // Whole numbers print without a decimal point, as in JavaScript.
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Value::Number(n) => write!(f, "{n}"),
+            Value::Str(s) => write!(f, "{s}"),
+            Value::Color(c) => {
+                let [r, g, b, a] = c.to_srgba().to_u8_array();
+                write!(f, "color({r}, {g}, {b}, {a})")
+            }
+            Value::Array(items) => {
+                // Locking it again would deadlock.
+                let array = Arc::as_ptr(items);
+                if open.contains(&array) {
+                    return write!(f, "[...]");
+                }
+                open.push(array);
+                write!(f, "[")?;
+                for (i, item) in items.lock().unwrap().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.write(f, open)?;
+                }
+                open.pop();
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, &mut Vec::new())
+    }
+}
+
+impl From<f32> for Value {
+    fn from(n: f32) -> Self {
+        Value::Number(n as f64)
+    }
+}
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+impl From<Color> for Value {
+    fn from(c: Color) -> Self {
+        Value::Color(c)
+    }
+}
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Str(s.into())
+    }
+}
+
+/// How a statement finished.
+enum Flow {
+    Normal,
+    Break(Pos),
+    Continue(Pos),
+    Return(Value),
+}
+
+/// Runs one callback of a script against its global variables.
+struct Interpreter<'a> {
+    program: &'a Program,
+    globals: &'a mut HashMap<String, Value>,
+    /// Block scopes of the function being run, innermost last; empty at the top level.
+    scopes: Vec<HashMap<String, Value>>,
+    steps: u32,
+    depth: u32,
+    /// How many `eval()`s are running, one inside the other.
+    nesting: u32,
+}
+
+impl<'a> Interpreter<'a> {
+    fn new(program: &'a Program, globals: &'a mut HashMap<String, Value>) -> Self {
+        Self {
+            program,
+            globals,
+            scopes: Vec::new(),
+            steps: 0,
+            depth: 0,
+            nesting: 0,
+        }
+    }
+
+    fn step(&mut self, pos: Pos) -> Result<(), ScriptError> {
+        self.steps += 1;
+        if self.steps > STEP_LIMIT {
+            return Err(ScriptError::new(
+                pos,
+                format!("still running after {STEP_LIMIT} steps; does a loop never end?"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Runs the top-level statements, which declare the script's globals.
+    fn run_top_level(&mut self) -> Result<(), ScriptError> {
+        for statement in &self.program.statements {
+            match self.exec(statement)? {
+                Flow::Normal => {}
+                Flow::Break(pos) => return Err(ScriptError::new(pos, "`break` outside a loop")),
+                Flow::Continue(pos) => {
+                    return Err(ScriptError::new(pos, "`continue` outside a loop"));
+                }
+                Flow::Return(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls the script's function `name` without arguments, if it has one.
+    fn call_if_defined(&mut self, name: &str) -> Result<(), ScriptError> {
+        if let Some(function) = self.program.functions.get(name) {
+            self.call(function, Vec::new(), Pos::default())?;
+        }
+        Ok(())
+    }
+
+    fn call(
+        &mut self,
+        function: &Function,
+        args: Vec<Value>,
+        pos: Pos,
+    ) -> Result<Value, ScriptError> {
+        self.step(pos)?;
+        if self.depth >= DEPTH_LIMIT {
+            return Err(ScriptError::new(pos, "too much recursion"));
+        }
+        // Missing arguments are `null` and extra ones are ignored, as in JavaScript.
+        let mut scope = HashMap::new();
+        let mut args = args.into_iter();
+        for param in &function.params {
+            scope.insert(param.clone(), args.next().unwrap_or(Value::Null));
+        }
+        let outer = std::mem::replace(&mut self.scopes, vec![scope]);
+        self.depth += 1;
+        let result = self.exec_block(&function.body);
+        self.depth -= 1;
+        self.scopes = outer;
+        match result? {
+            Flow::Normal => Ok(Value::Null),
+            Flow::Return(value) => Ok(value),
+            Flow::Break(pos) => Err(ScriptError::new(pos, "`break` outside a loop")),
+            Flow::Continue(pos) => Err(ScriptError::new(pos, "`continue` outside a loop")),
+        }
+    }
+
+    fn exec_block(&mut self, statements: &[Stmt]) -> Result<Flow, ScriptError> {
+        for statement in statements {
+            match self.exec(statement)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    /// Runs `f` in a new block scope.
+    fn scoped<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.scopes.push(HashMap::new());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    fn exec(&mut self, statement: &Stmt) -> Result<Flow, ScriptError> {
+        match statement {
+            Stmt::Declare(names) => {
+                for (name, value) in names {
+                    let value = match value {
+                        Some(value) => self.eval(value)?,
+                        None => Value::Null,
+                    };
+                    match self.scopes.last_mut() {
+                        Some(scope) => scope.insert(name.clone(), value),
+                        None => self.globals.insert(name.clone(), value),
+                    };
+                }
+            }
+            Stmt::Expr(expr) => {
+                self.eval(expr)?;
+            }
+            Stmt::Block(statements) => return self.scoped(|this| this.exec_block(statements)),
+            Stmt::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                if self.eval(condition)?.is_truthy() {
+                    return self.scoped(|this| this.exec(then));
+                } else if let Some(otherwise) = otherwise {
+                    return self.scoped(|this| this.exec(otherwise));
+                }
+            }
+            Stmt::While {
+                condition,
+                body,
+                pos,
+            } => {
+                while self.eval(condition)?.is_truthy() {
+                    self.step(*pos)?;
+                    match self.scoped(|this| this.exec(body))? {
+                        Flow::Break(_) => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue(_) => {}
+                    }
+                }
+            }
+            Stmt::For {
+                init,
+                condition,
+                step,
+                body,
+                pos,
+            } => {
+                return self.scoped(|this| {
+                    if let Some(init) = init {
+                        this.exec(init)?;
+                    }
+                    loop {
+                        if let Some(condition) = condition
+                            && !this.eval(condition)?.is_truthy()
+                        {
+                            break;
+                        }
+                        this.step(*pos)?;
+                        match this.scoped(|this| this.exec(body))? {
+                            Flow::Break(_) => break,
+                            Flow::Return(value) => return Ok(Flow::Return(value)),
+                            Flow::Normal | Flow::Continue(_) => {}
+                        }
+                        if let Some(step) = step {
+                            this.eval(step)?;
+                        }
+                    }
+                    Ok(Flow::Normal)
+                });
+            }
+            Stmt::Return(value) => {
+                let value = match value {
+                    Some(value) => self.eval(value)?,
+                    None => Value::Null,
+                };
+                return Ok(Flow::Return(value));
+            }
+            Stmt::Break(pos) => return Ok(Flow::Break(*pos)),
+            Stmt::Continue(pos) => return Ok(Flow::Continue(*pos)),
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn lookup(&self, name: &str, pos: Pos) -> Result<Value, ScriptError> {
+        let local = self.scopes.iter().rev().find_map(|scope| scope.get(name));
+        if let Some(value) = local.or_else(|| self.globals.get(name)) {
+            return Ok(value.clone());
+        }
+        builtins::variable(name).ok_or_else(|| {
+            let known = self
+                .scopes
+                .iter()
+                .chain([&*self.globals])
+                .flat_map(|s| s.keys());
+            let known = known
+                .map(String::as_str)
+                .chain(builtins::VARIABLES.iter().copied());
+            ScriptError::new(
+                pos,
+                format!("`{name}` is not declared{}", suggestion(name, known)),
+            )
+        })
+    }
+
+    fn assign_variable(&mut self, name: &str, value: Value, pos: Pos) -> Result<(), ScriptError> {
+        let slot = match self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+        {
+            Some(slot) => slot,
+            None => self.globals.get_mut(name).ok_or_else(|| {
+                ScriptError::new(
+                    pos,
+                    format!("`{name}` is not declared; declare it first with `let {name}`"),
+                )
+            })?,
+        };
+        *slot = value;
+        Ok(())
+    }
+
+    fn set_place(&mut self, target: &Expr, value: Value) -> Result<(), ScriptError> {
+        match &target.kind {
+            ExprKind::Variable(name) => self.assign_variable(name, value, target.pos),
+            ExprKind::Index(array, index) => {
+                let array = self.eval(array)?;
+                let index = self.eval(index)?;
+                let Value::Array(items) = &array else {
+                    return Err(ScriptError::new(
+                        target.pos,
+                        format!("can't assign to an element of {}", array.type_name()),
+                    ));
+                };
+                let mut items = items.lock().unwrap();
+                // Assigning one past the end appends, so arrays can grow.
+                if number(&index, target.pos)? == items.len() as f64 {
+                    items.push(value);
+                } else {
+                    let i = index_of(&index, items.len(), target.pos)?;
+                    items[i] = value;
+                }
+                Ok(())
+            }
+            _ => unreachable!("the parser only accepts variables and elements as places"),
+        }
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Value, ScriptError> {
+        if self.nesting >= NESTING_LIMIT {
+            return Err(too_deep(expr.pos));
+        }
+        self.nesting += 1;
+        let value = self.unnested_eval(expr);
+        self.nesting -= 1;
+        value
+    }
+
+    /// Evaluates `expr` itself; each kind of expression that evaluates others
+    /// does so in its own function, keeping this one's stack frame small.
+    fn unnested_eval(&mut self, expr: &Expr) -> Result<Value, ScriptError> {
+        let pos = expr.pos;
+        match &expr.kind {
+            ExprKind::Number(n) => Ok(Value::Number(*n)),
+            ExprKind::Str(s) => Ok(Value::Str(s.clone())),
+            ExprKind::Bool(b) => Ok(Value::Bool(*b)),
+            ExprKind::Null => Ok(Value::Null),
+            ExprKind::Array(items) => self.eval_array(items),
+            ExprKind::Variable(name) => self.lookup(name, pos),
+            ExprKind::Negate(operand) => self.eval_negate(operand, pos),
+            ExprKind::Not(operand) => self.eval_not(operand),
+            ExprKind::Binary(op, left, right) => self.eval_binary(*op, left, right, pos),
+            ExprKind::Logical(and, left, right) => self.eval_logical(*and, left, right),
+            ExprKind::Conditional(condition, then, otherwise) => {
+                self.eval_conditional(condition, then, otherwise)
+            }
+            ExprKind::Assign { target, op, value } => self.eval_assign(target, *op, value, pos),
+            ExprKind::Increment {
+                target,
+                delta,
+                prefix,
+            } => self.eval_increment(target, *delta, *prefix, pos),
+            ExprKind::Call(name, args) => self.eval_call(name, args, pos),
+            ExprKind::Method(object, name, args) => self.eval_method(object, name, args, pos),
+            ExprKind::Index(object, index) => self.eval_index(object, index, pos),
+            ExprKind::Member(object, name) => self.eval_member(object, name, pos),
+        }
+    }
+
+    fn eval_array(&mut self, items: &[Expr]) -> Result<Value, ScriptError> {
+        Ok(Value::Array(Arc::new(Mutex::new(self.eval_all(items)?))))
+    }
+
+    fn eval_negate(&mut self, operand: &Expr, pos: Pos) -> Result<Value, ScriptError> {
+        Ok(Value::Number(-number(&self.eval(operand)?, pos)?))
+    }
+
+    fn eval_not(&mut self, operand: &Expr) -> Result<Value, ScriptError> {
+        Ok(Value::Bool(!self.eval(operand)?.is_truthy()))
+    }
+
+    fn eval_binary(
+        &mut self,
+        op: BinaryOp,
+        left: &Expr,
+        right: &Expr,
+        pos: Pos,
+    ) -> Result<Value, ScriptError> {
+        let left = self.eval(left)?;
+        let right = self.eval(right)?;
+        binary(op, &left, &right, pos)
+    }
+
+    fn eval_logical(&mut self, and: bool, left: &Expr, right: &Expr) -> Result<Value, ScriptError> {
+        let left = self.eval(left)?;
+        if left.is_truthy() == and {
+            self.eval(right)
+        } else {
+            Ok(left)
+        }
+    }
+
+    fn eval_conditional(
+        &mut self,
+        condition: &Expr,
+        then: &Expr,
+        otherwise: &Expr,
+    ) -> Result<Value, ScriptError> {
+        if self.eval(condition)?.is_truthy() {
+            self.eval(then)
+        } else {
+            self.eval(otherwise)
+        }
+    }
+
+    fn eval_assign(
+        &mut self,
+        target: &Expr,
+        op: Option<BinaryOp>,
+        value: &Expr,
+        pos: Pos,
+    ) -> Result<Value, ScriptError> {
+        let mut value = self.eval(value)?;
+        if let Some(op) = op {
+            value = binary(op, &self.eval(target)?, &value, pos)?;
+        }
+        self.set_place(target, value.clone())?;
+        Ok(value)
+    }
+
+    fn eval_increment(
+        &mut self,
+        target: &Expr,
+        delta: f64,
+        prefix: bool,
+        pos: Pos,
+    ) -> Result<Value, ScriptError> {
+        let old = number(&self.eval(target)?, pos)?;
+        self.set_place(target, Value::Number(old + delta))?;
+        Ok(Value::Number(if prefix { old + delta } else { old }))
+    }
+
+    fn eval_call(&mut self, name: &str, args: &[Expr], pos: Pos) -> Result<Value, ScriptError> {
+        let args = self.eval_all(args)?;
+        if let Some(function) = self.program.functions.get(name) {
+            return self.call(function, args, pos);
+        }
+        match builtins::call(name, &args) {
+            Ok(Some(value)) => Ok(value),
+            Ok(None) => {
+                let known = self.program.functions.keys().map(String::as_str);
+                let known = known.chain(builtins::FUNCTIONS.iter().copied());
+                Err(ScriptError::new(
+                    pos,
+                    format!("unknown function `{name}`{}", suggestion(name, known)),
+                ))
+            }
+            Err(message) => Err(ScriptError::new(pos, message)),
+        }
+    }
+
+    fn eval_method(
+        &mut self,
+        object: &Expr,
+        name: &str,
+        args: &[Expr],
+        pos: Pos,
+    ) -> Result<Value, ScriptError> {
+        let object = self.eval(object)?;
+        let args = self.eval_all(args)?;
+        method(&object, name, args, pos)
+    }
+
+    fn eval_index(&mut self, object: &Expr, index: &Expr, pos: Pos) -> Result<Value, ScriptError> {
+        let object = self.eval(object)?;
+        let index = self.eval(index)?;
+        match &object {
+            Value::Array(items) => {
+                let items = items.lock().unwrap();
+                Ok(items[index_of(&index, items.len(), pos)?].clone())
+            }
+            Value::Str(s) => {
+                let chars: Vec<char> = s.chars().collect();
+                let c = chars[index_of(&index, chars.len(), pos)?];
+                Ok(Value::Str(c.to_string().into()))
+            }
+            _ => Err(ScriptError::new(
+                pos,
+                format!("can't index {}", object.type_name()),
+            )),
+        }
+    }
+
+    fn eval_member(&mut self, object: &Expr, name: &str, pos: Pos) -> Result<Value, ScriptError> {
+        let object = self.eval(object)?;
+        match (&object, name) {
+            (Value::Array(items), "length") => {
+                Ok(Value::Number(items.lock().unwrap().len() as f64))
+            }
+            (Value::Str(s), "length") => Ok(Value::Number(s.chars().count() as f64)),
+            _ => Err(ScriptError::new(
+                pos,
+                format!("{} has no `{name}`", object.type_name()),
+            )),
+        }
+    }
+
+    fn eval_all(&mut self, exprs: &[Expr]) -> Result<Vec<Value>, ScriptError> {
+        exprs.iter().map(|expr| self.eval(expr)).collect()
+    }
+}
+
+fn too_deep(pos: Pos) -> ScriptError {
+    ScriptError::new(
+        pos,
+        format!("expressions nested more than {NESTING_LIMIT} deep"),
+    )
+}
+
+fn number(value: &Value, pos: Pos) -> Result<f64, ScriptError> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        _ => Err(ScriptError::new(
+            pos,
+            format!("expected a number, found {}", value.type_name()),
+        )),
+    }
+}
+
+/// Checks that `index` is a whole number below `len`.
+fn index_of(index: &Value, len: usize, pos: Pos) -> Result<usize, ScriptError> {
+    let n = number(index, pos)?;
+    if n.fract() != 0.0 || n < 0.0 || n >= len as f64 {
+        return Err(ScriptError::new(
+            pos,
+            format!("index {index} is out of range for length {}", len),
+        ));
+    }
+    Ok(n as usize)
+}
+
+fn binary(op: BinaryOp, left: &Value, right: &Value, pos: Pos) -> Result<Value, ScriptError> {
+    Ok(match (op, left, right) {
+        (BinaryOp::Equal, ..) => Value::Bool(left.equals(right)),
+        (BinaryOp::NotEqual, ..) => Value::Bool(!left.equals(right)),
+        (BinaryOp::Add, Value::Str(_), _) | (BinaryOp::Add, _, Value::Str(_)) => {
+            Value::Str(format!("{left}{right}").into())
+        }
+        (_, Value::Number(a), Value::Number(b)) => match op {
+            BinaryOp::Add => Value::Number(a + b),
+            BinaryOp::Subtract => Value::Number(a - b),
+            BinaryOp::Multiply => Value::Number(a * b),
+            BinaryOp::Divide => Value::Number(a / b),
+            BinaryOp::Remainder => Value::Number(a % b),
+            BinaryOp::Less => Value::Bool(a < b),
+            BinaryOp::LessEqual => Value::Bool(a <= b),
+            BinaryOp::Greater => Value::Bool(a > b),
+            BinaryOp::GreaterEqual => Value::Bool(a >= b),
+            BinaryOp::Equal | BinaryOp::NotEqual => unreachable!(),
+        },
+        (BinaryOp::Less, Value::Str(a), Value::Str(b)) => Value::Bool(a < b),
+        (BinaryOp::LessEqual, Value::Str(a), Value::Str(b)) => Value::Bool(a <= b),
+        (BinaryOp::Greater, Value::Str(a), Value::Str(b)) => Value::Bool(a > b),
+        (BinaryOp::GreaterEqual, Value::Str(a), Value::Str(b)) => Value::Bool(a >= b),
+        _ => {
+            return Err(ScriptError::new(
+                pos,
+                format!(
+                    "can't use `{}` on {} and {}",
+                    op.symbol(),
+                    left.type_name(),
+                    right.type_name()
+                ),
+            ));
+        }
+    })
+}
+
+fn method(object: &Value, name: &str, args: Vec<Value>, pos: Pos) -> Result<Value, ScriptError> {
+    let Value::Array(items) = object else {
+        return Err(ScriptError::new(
+            pos,
+            format!("{} has no method `{name}`", object.type_name()),
+        ));
+    };
+    let mut items = items.lock().unwrap();
+    Ok(match name {
+        "push" => {
+            items.extend(args);
+            Value::Number(items.len() as f64)
+        }
+        "pop" => items.pop().unwrap_or(Value::Null),
+        "shift" if items.is_empty() => Value::Null,
+        "shift" => items.remove(0),
+        "splice" => {
+            let start = args
+                .first()
+                .map(|start| index_of(start, items.len() + 1, pos));
+            let start = start.transpose()?.unwrap_or(0);
+            let count = match args.get(1) {
+                Some(count) => number(count, pos)?.max(0.0) as usize,
+                None => items.len(),
+            };
+            let end = (start + count).min(items.len());
+            let removed = items.drain(start..end).collect();
+            Value::Array(Arc::new(Mutex::new(removed)))
+        }
+        _ => {
+            return Err(ScriptError::new(
+                pos,
+                format!("arrays have no method `{name}`"),
+            ));
+        }
+    })
+}
+
+/// ", did you mean `x`?" for the known name closest to `name`, if one is close.
+fn suggestion<'a>(name: &str, known: impl Iterator<Item = &'a str>) -> String {
+    let best = known
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 2.max(name.len() / 3))
+        .min();
+    match best {
+        Some((_, candidate)) => format!("; did you mean `{candidate}`?"),
+        None => String::new(),
+    }
+}
+
+/// Levenshtein distance, ignoring case so `strokeweight` finds `strokeWeight`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (diagonal + usize::from(ca != cb))
+                .min(row[j] + 1)
+                .min(above + 1);
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// A script's globals between frames, and whether it is still running.
+#[derive(Component, Default)]
+pub(super) struct ScriptRuntime {
+    globals: HashMap<String, Value>,
+    started: bool,
+    /// The report of the error that stopped the script, shown until it restarts.
+    error: Option<String>,
+}
+
+/// Empties every array the globals reach, so that arrays stored inside each
+/// other don't keep each other alive after the script stops or restarts.
+impl Drop for ScriptRuntime {
+    fn drop(&mut self) {
+        let mut values: Vec<Value> = self.globals.drain().map(|(_, value)| value).collect();
+        while let Some(value) = values.pop() {
+            if let Value::Array(items) = value
+                && let Ok(mut items) = items.lock()
+            {
+                values.append(&mut items);
+            }
+        }
+    }
+}
+
+impl ScriptRuntime {
+    /// Runs the top level and `setup()` the first time, then the callbacks
+    /// for this frame's input `events`. Like a `Sketch`'s, they run every
+    /// frame, whether or not the canvas draws, so they may call `loop()` or
+    /// `redraw()` after `noLoop()`.
+    fn input(&mut self, script: &Script, events: &[&str]) {
+        let start = !std::mem::replace(&mut self.started, true);
+        self.run(script, |interpreter| {
+            if start {
+                interpreter.run_top_level()?;
+                interpreter.call_if_defined("setup")?;
+            }
+            for event in events {
+                interpreter.call_if_defined(event)?;
+            }
+            if is_under_cursor() && (mouse_x(), mouse_y()) != (pmouse_x(), pmouse_y()) {
+                interpreter.call_if_defined(if mouse_is_pressed() {
+                    "mouseDragged"
+                } else {
+                    "mouseMoved"
+                })?;
+            }
+            Ok(())
+        });
+    }
+
+    /// Runs `draw()`, or shows the error that stopped the script.
+    fn frame(&mut self, script: &Script) {
+        self.run(script, |interpreter| interpreter.call_if_defined("draw"));
+        if let Some(report) = &self.error {
+            show_error(report);
+        }
+    }
+
+    /// Runs `f` on the script's globals unless an error has stopped it, and
+    /// stops it if `f` fails.
+    fn run(
+        &mut self,
+        script: &Script,
+        f: impl FnOnce(&mut Interpreter) -> Result<(), ScriptError>,
+    ) {
+        if self.error.is_some() {
+            return;
+        }
+        let result = script
+            .program
+            .as_ref()
+            .map_err(Clone::clone)
+            .and_then(|program| f(&mut Interpreter::new(program, &mut self.globals)));
+        if let Err(error) = result {
+            let report = error.report(&script.name, &script.source);
+            error!("{report}");
+            self.error = Some(report);
+            // So that the report is shown even after `noLoop()`.
+            redraw();
+        }
+    }
+}
+
+fn show_error(report: &str) {
+    reset_matrix();
+    background(color(70.0, 20.0, 30.0));
+    fill(color(255.0, 230.0, 230.0));
+    text_size(13.0);
+    text_align(TextAlign::Left, TextBaseline::Top);
+    text(report, 10.0, 10.0);
+}
+
+/// Loads `.pjs` script files.
+#[derive(Default)]
+pub struct ScriptLoader;
+
+impl AssetLoader for ScriptLoader {
+    type Asset = Script;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Script, std::io::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let source = String::from_utf8(bytes)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+        let name = load_context.path().display().to_string();
+        let program = parse::parse(&source).map(Arc::new);
+        Ok(Script {
+            name,
+            source,
+            program,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pjs"]
+    }
+}
+
+/// Runs every script for a frame. A changed file restarts its script, and F5
+/// reloads every script file, restarting them even if they haven't changed.
+pub(super) fn run_scripts(
+    mut sketch: SketchContext,
+    mut scripts: Query<(Entity, &ScriptSource, &mut ScriptRuntime)>,
+    assets: Res<Assets<Script>>,
+    asset_server: Res<AssetServer>,
+    mut changes: EventReader<AssetEvent<Script>>,
+    mut buttons: EventReader<MouseButtonInput>,
+    mut keys: EventReader<KeyboardInput>,
+) {
+    let changed: Vec<_> = changes
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    let mouse_events: Vec<_> = buttons
+        .read()
+        .map(|event| match event.state {
+            ButtonState::Pressed => "mousePressed",
+            ButtonState::Released => "mouseReleased",
+        })
+        .collect();
+    let mut key_events = Vec::new();
+    let mut reload = false;
+    for event in keys.read() {
+        reload |= event.key_code == KeyCode::F5 && event.state == ButtonState::Pressed;
+        key_events.push(match event.state {
+            ButtonState::Pressed => "keyPressed",
+            ButtonState::Released => "keyReleased",
+        });
+    }
+
+    for (canvas, source, mut runtime) in &mut scripts {
+        if reload && let Some(path) = source.0.path() {
+            // Reloading replaces the asset even if the file is the same, which
+            // shows up as a change below on a later frame.
+            asset_server.reload(path.clone());
+        }
+        if changed.contains(&source.0.id()) {
+            *runtime = ScriptRuntime::default();
+            if let Some(path) = source.0.path() {
+                info!("restarted {path}");
+            }
+        }
+        if let Some(script) = assets.get(&source.0) {
+            // The same canvases get these as a `Sketch` would in `run_input`.
+            let mut events = Vec::new();
+            if sketch.under_cursor(canvas) {
+                events.extend(&mouse_events);
+            }
+            if sketch.focused(canvas) {
+                events.extend(&key_events);
+            }
+            sketch.input(canvas, || runtime.input(script, &events));
+            sketch.draw(canvas, || runtime.frame(script));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pacing::run_draws;
+    use super::super::{SketchState, run_user};
+    use super::*;
+
+    /// Runs `source`'s top level, then the functions named in `calls`, and
+    /// gives back the globals it declared.
+    fn run(source: &str, calls: &[&str]) -> Result<HashMap<String, Value>, ScriptError> {
+        let program = parse::parse(source)?;
+        let mut globals = HashMap::new();
+        let mut interpreter = Interpreter::new(&program, &mut globals);
+        interpreter.run_top_level()?;
+        for name in calls {
+            interpreter.call_if_defined(name)?;
+        }
+        Ok(globals)
+    }
+
+    fn global(source: &str, name: &str) -> String {
+        match run(source, &[]) {
+            Ok(globals) => globals[name].to_string(),
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    fn error(source: &str) -> String {
+        match run(source, &["draw"]) {
+            Ok(_) => panic!("`{source}` ran without an error"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn runs_scripts() {
+        let source = "
+            let total = 0;
+            let items = [];
+            function add(a, b) { return a + b; }
+            for (let i = 0; i < 5; i++) {
+                if (i == 3) continue;
+                items.push(i);
+                total = add(total, i);
+            }
+            let label = \"total \" + total + \" of \" + items.length;
+        ";
+        assert_eq!(global(source, "total"), "7");
+        assert_eq!(global(source, "items"), "[0, 1, 2, 4]");
+        assert_eq!(global(source, "label"), "total 7 of 4");
+    }
+
+    #[test]
+    fn callbacks_share_the_globals() {
+        let globals = run(
+            "let frames = 0; void setup() { frames = 10; } void draw() { frames++; }",
+            &["setup", "draw", "draw"],
+        )
+        .unwrap();
+        assert_eq!(globals["frames"].to_string(), "12");
+    }
+
+    #[test]
+    fn runtime_errors_have_positions() {
+        assert_eq!(
+            error("let speed = 1;\nlet y = 2 * sped;"),
+            "line 2, column 13: `sped` is not declared; did you mean `speed`?"
+        );
+        assert_eq!(
+            error("let count = 1;\nvoid draw() {\n  cuont = 2;\n}"),
+            "line 3, column 3: `cuont` is not declared; declare it first with `let cuont`"
+        );
+        assert_eq!(
+            error("let a = [1, 2];\nlet b = a[2];"),
+            "line 2, column 10: index 2 is out of range for length 2"
+        );
+    }
+
+    #[test]
+    fn step_limit_stops_endless_loops() {
+        assert_eq!(
+            error("void draw() {\n  while (true) {}\n}"),
+            format!(
+                "line 2, column 3: still running after {STEP_LIMIT} steps; does a loop never end?"
+            )
+        );
+    }
+
+    #[test]
+    fn depth_limit_stops_endless_recursion() {
+        assert_eq!(
+            error("function f(n) {\n  return f(n + 1);\n}\nf(0);"),
+            "line 2, column 10: too much recursion"
+        );
+        // Just under the limit still runs.
+        let source = format!(
+            "function f(n) {{ return n == 0 ? 0 : 1 + f(n - 1); }}\nlet x = f({});",
+            DEPTH_LIMIT - 1
+        );
+        assert_eq!(global(&source, "x"), (DEPTH_LIMIT - 1).to_string());
+    }
+
+    #[test]
+    fn nesting_limit_stops_deep_expressions() {
+        // Each call nests twenty expressions in the one before, so the
+        // nesting limit is reached long before the depth limit.
+        let negations = format!("{}f(){}", "-(".repeat(20), ")".repeat(20));
+        let source = format!("function f() {{\n  return {negations};\n}}\nf();");
+        assert!(error(&source).ends_with(&format!(
+            "expressions nested more than {NESTING_LIMIT} deep"
+        )));
+    }
+
+    #[test]
+    fn arrays_inside_themselves_print_and_are_freed() {
+        let globals = run(
+            "let a = [1];\na.push(a);\nlet b = [a, a];\nlet s = \"\" + a;",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(globals["s"].to_string(), "[1, [...]]");
+        // Only an array inside itself is cut short, not one seen twice.
+        assert_eq!(globals["b"].to_string(), "[[1, [...]], [1, [...]]]");
+
+        let Value::Array(a) = &globals["a"] else {
+            panic!("`a` is not an array");
+        };
+        let a = Arc::downgrade(a);
+        let mut runtime = ScriptRuntime::default();
+        runtime.globals = globals;
+        drop(runtime);
+        assert!(a.upgrade().is_none());
+    }
+
+    #[test]
+    fn input_callbacks_run_after_no_loop() {
+        let source = "
+            let presses = 0;
+            void setup() { noLoop(); }
+            void draw() {}
+            void mousePressed() { presses++; loop(); }
+        ";
+        let script = Script {
+            name: "test.pjs".into(),
+            source: source.into(),
+            program: parse::parse(source).map(Arc::new),
+        };
+        let mut state = SketchState::default();
+        let mut runtime = ScriptRuntime::default();
+        let mut frame = |state: &mut SketchState, now: f64, events: &[&str]| {
+            state.pacing.schedule(now);
+            run_user(state, || runtime.input(&script, events));
+            run_draws(state, || runtime.frame(&script));
+            state.pacing.draws()
+        };
+
+        // Stopped by `setup()`, after drawing once.
+        assert!(frame(&mut state, 0.0, &[]));
+        assert!(!frame(&mut state, 1.0, &[]));
+        // The click still reaches the script, which starts it again.
+        assert!(!frame(&mut state, 2.0, &["mousePressed"]));
+        assert!(frame(&mut state, 3.0, &[]));
+        assert_eq!(runtime.globals["presses"].to_string(), "1");
+    }
+}
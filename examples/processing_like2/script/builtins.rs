@@ -0,0 +1,793 @@
+//! The functions and variables scripts get for free, under their Processing
+//! names, each forwarding to the sketch function of the same meaning.
+
+use bevy::prelude::*;
+
+use super::super::blend::{BlendMode, blend_mode};
+use super::super::color::{
+    ColorMode, alpha, blue, brightness, color, color_alpha, color_mode, color_mode_max,
+    color_mode_ranges, gray, gray_alpha, green, hex_color, hue, lerp_color, red, saturation,
+};
+use super::super::math::{
+    constrain, dist, lerp, map, noise, noise_detail, noise_seed, random, random_gaussian,
+    random_seed,
+};
+use super::super::pacing::{delta_time, frame_rate, is_looping, r#loop, no_loop, redraw};
+use super::super::pixels::{Filter, filter, get, load_pixels, set, update_pixels};
+use super::super::stroke::{StrokeCap, StrokeJoin, miter_limit, stroke_cap, stroke_join};
+use super::super::text::{
+    TextAlign, TextBaseline, text, text_align, text_leading, text_size, text_width,
+};
+use super::super::{
+    ArcMode, EndShape, ShapeKind, ShapeMode, arc, background, begin_contour, begin_shape, bezier,
+    bezier_vertex, circle, curve, curve_vertex, ellipse, ellipse_mode, end_contour, end_shape,
+    fill, frame_count, full_screen, height, key, key_is_pressed, layer, line, millis, mouse_button,
+    mouse_is_pressed, mouse_x, mouse_y, no_fill, no_stroke, pmouse_x, pmouse_y, point, pop_matrix,
+    push_matrix, quad, quadratic_vertex, rect, rect_mode, rect_radii, rect_rounded, reset_matrix,
+    rotate, scale, size, square, stroke, stroke_weight, translate, triangle, vertex, width,
+};
+use super::Value;
+
+/// Every function `call()` knows, for suggesting a name when one is misspelled.
+pub const FUNCTIONS: &[&str] = &[
+    "size",
+    "frameRate",
+    "noLoop",
+    "loop",
+    "isLooping",
+    "redraw",
+    "fullScreen",
+    "background",
+    "fill",
+    "noFill",
+    "stroke",
+    "noStroke",
+    "strokeWeight",
+    "strokeCap",
+    "strokeJoin",
+    "miterLimit",
+    "blendMode",
+    "layer",
+    "pushMatrix",
+    "popMatrix",
+    "resetMatrix",
+    "translate",
+    "rotate",
+    "scale",
+    "point",
+    "line",
+    "rect",
+    "ellipse",
+    "triangle",
+    "quad",
+    "square",
+    "circle",
+    "rectMode",
+    "ellipseMode",
+    "arc",
+    "bezier",
+    "curve",
+    "beginShape",
+    "vertex",
+    "bezierVertex",
+    "quadraticVertex",
+    "curveVertex",
+    "beginContour",
+    "endContour",
+    "endShape",
+    "text",
+    "textSize",
+    "textLeading",
+    "textAlign",
+    "textWidth",
+    "color",
+    "colorMode",
+    "lerpColor",
+    "loadPixels",
+    "updatePixels",
+    "get",
+    "set",
+    "filter",
+    "red",
+    "green",
+    "blue",
+    "alpha",
+    "hue",
+    "saturation",
+    "brightness",
+    "random",
+    "randomSeed",
+    "randomGaussian",
+    "noise",
+    "noiseSeed",
+    "noiseDetail",
+    "map",
+    "constrain",
+    "lerp",
+    "dist",
+    "sq",
+    "sqrt",
+    "pow",
+    "exp",
+    "log",
+    "abs",
+    "floor",
+    "ceil",
+    "round",
+    "min",
+    "max",
+    "sin",
+    "cos",
+    "tan",
+    "asin",
+    "acos",
+    "atan",
+    "atan2",
+    "radians",
+    "degrees",
+    "int",
+    "float",
+    "millis",
+    "print",
+    "println",
+];
+
+/// Every variable `variable()` knows, for the same reason.
+pub const VARIABLES: &[&str] = &[
+    "width",
+    "height",
+    "frameCount",
+    "deltaTime",
+    "mouseX",
+    "mouseY",
+    "pmouseX",
+    "pmouseY",
+    "mouseIsPressed",
+    "mouseButton",
+    "key",
+    "keyIsPressed",
+    "PI",
+    "HALF_PI",
+    "QUARTER_PI",
+    "TWO_PI",
+    "TAU",
+    "RGB",
+    "HSB",
+    "CLOSE",
+    "OPEN",
+    "CHORD",
+    "CORNER",
+    "CORNERS",
+    "RADIUS",
+    "PIE",
+    "ROUND",
+    "SQUARE",
+    "PROJECT",
+    "MITER",
+    "BEVEL",
+    "POINTS",
+    "LINES",
+    "TRIANGLES",
+    "TRIANGLE_STRIP",
+    "TRIANGLE_FAN",
+    "QUADS",
+    "QUAD_STRIP",
+    "LEFT",
+    "CENTER",
+    "RIGHT",
+    "TOP",
+    "BOTTOM",
+    "BASELINE",
+    "BLEND",
+    "ADD",
+    "SUBTRACT",
+    "LIGHTEST",
+    "DARKEST",
+    "MULTIPLY",
+    "SCREEN",
+    "GRAY",
+    "INVERT",
+    "THRESHOLD",
+    "BLUR",
+];
+
+/// The value of a built-in variable or constant. Constants are strings
+/// holding their own name, which the functions taking them look for.
+pub fn variable(name: &str) -> Option<Value> {
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU};
+    Some(match name {
+        "width" => width().into(),
+        "height" => height().into(),
+        "frameCount" => (frame_count() as f32).into(),
+        "deltaTime" => delta_time().into(),
+        "mouseX" => mouse_x().into(),
+        "mouseY" => mouse_y().into(),
+        "pmouseX" => pmouse_x().into(),
+        "pmouseY" => pmouse_y().into(),
+        "mouseIsPressed" => mouse_is_pressed().into(),
+        "mouseButton" => match mouse_button() {
+            Some(MouseButton::Left) => "LEFT".into(),
+            Some(MouseButton::Right) => "RIGHT".into(),
+            Some(MouseButton::Middle) => "CENTER".into(),
+            _ => Value::Null,
+        },
+        "key" => match key() {
+            Some(c) => c.to_string().as_str().into(),
+            None => Value::Null,
+        },
+        "keyIsPressed" => key_is_pressed().into(),
+        "PI" => PI.into(),
+        "HALF_PI" => FRAC_PI_2.into(),
+        "QUARTER_PI" => FRAC_PI_4.into(),
+        "TWO_PI" | "TAU" => TAU.into(),
+        _ if VARIABLES.contains(&name) => name.into(),
+        _ => return None,
+    })
+}
+
+/// Calls the built-in function `name`, or returns `None` if there isn't one.
+pub fn call(name: &str, values: &[Value]) -> Result<Option<Value>, String> {
+    let args = Args { name, values };
+    if command(name, args)? {
+        return Ok(Some(Value::Null));
+    }
+    Ok(Some(match name {
+        "textWidth" => match values {
+            [Value::Str(s)] => text_width(s).into(),
+            _ => return Err(args.usage("a string")),
+        },
+        "color" => args.color()?.into(),
+        "isLooping" => {
+            args.none()?;
+            is_looping().into()
+        }
+        "get" => {
+            let [x, y] = args.numbers()?;
+            get(x, y).into()
+        }
+        // Processing's conversions: `int()` truncates and `float()` parses strings.
+        "int" => {
+            let [x] = args.numbers()?;
+            x.trunc().into()
+        }
+        "float" => match values {
+            [Value::Number(n)] => Value::Number(*n),
+            [Value::Str(s)] => s
+                .trim()
+                .parse()
+                .map_or(Value::Number(f64::NAN), Value::Number),
+            _ => return Err(args.usage("a number or a string")),
+        },
+        "lerpColor" => match values {
+            [Value::Color(from), Value::Color(to), Value::Number(amount)] => {
+                lerp_color(*from, *to, *amount as f32).into()
+            }
+            _ => return Err(args.usage("two colors and a number")),
+        },
+        "red" | "green" | "blue" | "alpha" | "hue" | "saturation" | "brightness" => {
+            let [Value::Color(c)] = values else {
+                return Err(args.usage("a color"));
+            };
+            let channel = match name {
+                "red" => red,
+                "green" => green,
+                "blue" => blue,
+                "alpha" => alpha,
+                "hue" => hue,
+                "saturation" => saturation,
+                _ => brightness,
+            };
+            channel(*c).into()
+        }
+        // random(high) or random(low, high).
+        "random" => match values.len() {
+            1 => {
+                let [high] = args.numbers()?;
+                random(0.0, high).into()
+            }
+            _ => {
+                let [low, high] = args.numbers()?;
+                random(low, high).into()
+            }
+        },
+        "randomGaussian" => {
+            args.none()?;
+            random_gaussian().into()
+        }
+        "noise" => {
+            if values.is_empty() || values.len() > 3 {
+                return Err(args.usage("1 to 3 numbers"));
+            }
+            let [x, y, z] = args.padded()?;
+            noise(x, y, z).into()
+        }
+        "map" => {
+            let [value, start1, stop1, start2, stop2] = args.numbers()?;
+            map(value, start1, stop1, start2, stop2).into()
+        }
+        "constrain" => {
+            let [value, low, high] = args.numbers()?;
+            constrain(value, low, high).into()
+        }
+        "lerp" => {
+            let [start, stop, amount] = args.numbers()?;
+            lerp(start, stop, amount).into()
+        }
+        "dist" => {
+            let [x1, y1, x2, y2] = args.numbers()?;
+            dist(x1, y1, x2, y2).into()
+        }
+        "sq" | "sqrt" | "exp" | "log" | "abs" | "floor" | "ceil" | "round" | "sin" | "cos"
+        | "tan" | "asin" | "acos" | "atan" | "radians" | "degrees" => {
+            let [x] = args.numbers()?;
+            let f: fn(f32) -> f32 = match name {
+                "sq" => |x| x * x,
+                "sqrt" => f32::sqrt,
+                "exp" => f32::exp,
+                "log" => f32::ln,
+                "abs" => f32::abs,
+                "floor" => f32::floor,
+                "ceil" => f32::ceil,
+                "round" => f32::round,
+                "sin" => f32::sin,
+                "cos" => f32::cos,
+                "tan" => f32::tan,
+                "asin" => f32::asin,
+                "acos" => f32::acos,
+                "atan" => f32::atan,
+                "radians" => f32::to_radians,
+                _ => f32::to_degrees,
+            };
+            f(x).into()
+        }
+        "pow" => {
+            let [base, exponent] = args.numbers()?;
+            base.powf(exponent).into()
+        }
+        "atan2" => {
+            let [y, x] = args.numbers()?;
+            y.atan2(x).into()
+        }
+        "min" | "max" => {
+            if values.is_empty() {
+                return Err(args.usage("at least one number"));
+            }
+            let numbers = args.all_numbers()?;
+            let pick = if name == "min" { f32::min } else { f32::max };
+            numbers.into_iter().reduce(pick).unwrap_or_default().into()
+        }
+        "millis" => {
+            args.none()?;
+            (millis() as f32).into()
+        }
+        _ => return Ok(None),
+    }))
+}
+
+/// Runs the built-in `name` if it is one that only draws or changes settings.
+fn command(name: &str, args: Args) -> Result<bool, String> {
+    let values = args.values;
+    match name {
+        "size" => {
+            let [w, h] = args.numbers()?;
+            size(w, h)
+        }
+        "fullScreen" => {
+            args.none()?;
+            full_screen()
+        }
+        "frameRate" => {
+            let [fps] = args.numbers()?;
+            frame_rate(fps)
+        }
+        "noLoop" => {
+            args.none()?;
+            no_loop()
+        }
+        "loop" => {
+            args.none()?;
+            r#loop()
+        }
+        "redraw" => {
+            args.none()?;
+            redraw()
+        }
+        "background" => background(args.color()?),
+        "fill" => fill(args.color()?),
+        "noFill" => {
+            args.none()?;
+            no_fill()
+        }
+        "stroke" => stroke(args.color()?),
+        "noStroke" => {
+            args.none()?;
+            no_stroke()
+        }
+        "strokeWeight" => {
+            let [weight] = args.numbers()?;
+            stroke_weight(weight)
+        }
+        "layer" => {
+            let [n] = args.numbers()?;
+            layer(n as i32)
+        }
+        "blendMode" => blend_mode(args.constant(&[
+            ("BLEND", BlendMode::Blend),
+            ("ADD", BlendMode::Add),
+            ("SUBTRACT", BlendMode::Subtract),
+            ("LIGHTEST", BlendMode::Lightest),
+            ("DARKEST", BlendMode::Darkest),
+            ("MULTIPLY", BlendMode::Multiply),
+            ("SCREEN", BlendMode::Screen),
+        ])?),
+        "pushMatrix" => {
+            args.none()?;
+            push_matrix()
+        }
+        "popMatrix" => {
+            args.none()?;
+            pop_matrix()
+        }
+        "resetMatrix" => {
+            args.none()?;
+            reset_matrix()
+        }
+        "translate" => {
+            let [x, y] = args.numbers()?;
+            translate(x, y)
+        }
+        "rotate" => {
+            let [angle] = args.numbers()?;
+            rotate(angle)
+        }
+        "scale" => match values.len() {
+            1 => {
+                let [s] = args.numbers()?;
+                scale(s, s)
+            }
+            _ => {
+                let [sx, sy] = args.numbers()?;
+                scale(sx, sy)
+            }
+        },
+        "strokeCap" => stroke_cap(args.constant(&[
+            ("ROUND", StrokeCap::Round),
+            ("SQUARE", StrokeCap::Square),
+            ("PROJECT", StrokeCap::Project),
+        ])?),
+        "strokeJoin" => stroke_join(args.constant(&[
+            ("MITER", StrokeJoin::Miter),
+            ("BEVEL", StrokeJoin::Bevel),
+            ("ROUND", StrokeJoin::Round),
+        ])?),
+        "miterLimit" => {
+            let [limit] = args.numbers()?;
+            miter_limit(limit)
+        }
+        "rectMode" | "ellipseMode" => {
+            let mode = args.constant(&[
+                ("CORNER", ShapeMode::Corner),
+                ("CORNERS", ShapeMode::Corners),
+                ("CENTER", ShapeMode::Center),
+                ("RADIUS", ShapeMode::Radius),
+            ])?;
+            match name {
+                "rectMode" => rect_mode(mode),
+                _ => ellipse_mode(mode),
+            }
+        }
+        "point" => {
+            let [x, y] = args.numbers()?;
+            point(x, y)
+        }
+        "line" => {
+            let [x1, y1, x2, y2] = args.numbers()?;
+            line(x1, y1, x2, y2)
+        }
+        // One radius for every corner, or one each clockwise from the top left.
+        "rect" => match values.len() {
+            4 => {
+                let [a, b, c, d] = args.numbers()?;
+                rect(a, b, c, d)
+            }
+            5 => {
+                let [a, b, c, d, r] = args.numbers()?;
+                rect_rounded(a, b, c, d, r)
+            }
+            _ => {
+                let [a, b, c, d, tl, tr, br, bl] = args.numbers()?;
+                rect_radii(a, b, c, d, tl, tr, br, bl)
+            }
+        },
+        "square" => {
+            let [x, y, extent] = args.numbers()?;
+            square(x, y, extent)
+        }
+        "ellipse" => {
+            let [a, b, c, d] = args.numbers()?;
+            ellipse(a, b, c, d)
+        }
+        "circle" => {
+            let [x, y, extent] = args.numbers()?;
+            circle(x, y, extent)
+        }
+        "triangle" => {
+            let [x1, y1, x2, y2, x3, y3] = args.numbers()?;
+            triangle(x1, y1, x2, y2, x3, y3)
+        }
+        "quad" => {
+            let [x1, y1, x2, y2, x3, y3, x4, y4] = args.numbers()?;
+            quad(x1, y1, x2, y2, x3, y3, x4, y4)
+        }
+        "arc" => {
+            let (numbers, mode) = args.split(6);
+            let [cx, cy, w, h, start, stop] = numbers.numbers()?;
+            let mode = mode.constant_or(
+                ArcMode::Open,
+                &[
+                    ("OPEN", ArcMode::Open),
+                    ("CHORD", ArcMode::Chord),
+                    ("PIE", ArcMode::Pie),
+                ],
+            )?;
+            arc(cx, cy, w, h, start, stop, mode)
+        }
+        "bezier" => {
+            let [x1, y1, cx1, cy1, cx2, cy2, x2, y2] = args.numbers()?;
+            bezier(x1, y1, cx1, cy1, cx2, cy2, x2, y2)
+        }
+        "curve" => {
+            let [x1, y1, x2, y2, x3, y3, x4, y4] = args.numbers()?;
+            curve(x1, y1, x2, y2, x3, y3, x4, y4)
+        }
+        "beginShape" => begin_shape(args.constant_or(
+            ShapeKind::Polygon,
+            &[
+                ("POINTS", ShapeKind::Points),
+                ("LINES", ShapeKind::Lines),
+                ("TRIANGLES", ShapeKind::Triangles),
+                ("TRIANGLE_STRIP", ShapeKind::TriangleStrip),
+                ("TRIANGLE_FAN", ShapeKind::TriangleFan),
+                ("QUADS", ShapeKind::Quads),
+                ("QUAD_STRIP", ShapeKind::QuadStrip),
+            ],
+        )?),
+        "vertex" => {
+            let [x, y] = args.numbers()?;
+            vertex(x, y)
+        }
+        "bezierVertex" => {
+            let [cx1, cy1, cx2, cy2, x, y] = args.numbers()?;
+            bezier_vertex(cx1, cy1, cx2, cy2, x, y)
+        }
+        "quadraticVertex" => {
+            let [cx, cy, x, y] = args.numbers()?;
+            quadratic_vertex(cx, cy, x, y)
+        }
+        "curveVertex" => {
+            let [x, y] = args.numbers()?;
+            curve_vertex(x, y)
+        }
+        "beginContour" => {
+            args.none()?;
+            begin_contour()
+        }
+        "endContour" => {
+            args.none()?;
+            end_contour()
+        }
+        "endShape" => end_shape(args.constant_or(EndShape::Open, &[("CLOSE", EndShape::Close)])?),
+        "text" => {
+            let (content, position) = args.split(1);
+            let content = content.values.first().map(Value::to_string);
+            let [x, y] = position.numbers()?;
+            text(content.unwrap_or_default(), x, y)
+        }
+        "textSize" => {
+            let [size] = args.numbers()?;
+            text_size(size)
+        }
+        "textLeading" => {
+            let [leading] = args.numbers()?;
+            text_leading(leading)
+        }
+        "textAlign" => {
+            let (align, baseline) = args.split(1);
+            let align = align.constant(&[
+                ("LEFT", TextAlign::Left),
+                ("CENTER", TextAlign::Center),
+                ("RIGHT", TextAlign::Right),
+            ])?;
+            let baseline = baseline.constant_or(
+                TextBaseline::Baseline,
+                &[
+                    ("TOP", TextBaseline::Top),
+                    ("CENTER", TextBaseline::Center),
+                    ("BASELINE", TextBaseline::Baseline),
+                    ("BOTTOM", TextBaseline::Bottom),
+                ],
+            )?;
+            text_align(align, baseline)
+        }
+        "colorMode" => {
+            let (mode, ranges) = args.split(1);
+            let mode = mode.constant(&[("RGB", ColorMode::Rgb), ("HSB", ColorMode::Hsb)])?;
+            match ranges.values.len() {
+                0 => color_mode(mode),
+                1 => {
+                    let [max] = ranges.numbers()?;
+                    color_mode_max(mode, max, max, max, max);
+                }
+                3 => {
+                    let [max1, max2, max3] = ranges.numbers()?;
+                    color_mode_ranges(mode, max1, max2, max3);
+                }
+                _ => {
+                    let [max1, max2, max3, max_alpha] = ranges.numbers()?;
+                    color_mode_max(mode, max1, max2, max3, max_alpha);
+                }
+            }
+        }
+        "loadPixels" => {
+            args.none()?;
+            load_pixels()
+        }
+        "updatePixels" => {
+            args.none()?;
+            update_pixels()
+        }
+        "set" => {
+            let (position, c) = args.split(2);
+            let [x, y] = position.numbers()?;
+            set(x, y, c.color()?)
+        }
+        // Processing's defaults: a threshold of 0.5 and a blur radius of 1.
+        "filter" => {
+            let (kind, level) = args.split(1);
+            let kind = kind.constant(&[
+                ("GRAY", Filter::Gray),
+                ("INVERT", Filter::Invert),
+                ("THRESHOLD", Filter::Threshold(0.5)),
+                ("BLUR", Filter::Blur(1.0)),
+            ])?;
+            filter(match kind {
+                _ if level.values.is_empty() => kind,
+                Filter::Threshold(_) => Filter::Threshold(level.numbers::<1>()?[0]),
+                Filter::Blur(_) => Filter::Blur(level.numbers::<1>()?[0]),
+                Filter::Gray | Filter::Invert => {
+                    level.none()?;
+                    kind
+                }
+            })
+        }
+        "randomSeed" => {
+            let [seed] = args.numbers()?;
+            random_seed(seed as u64)
+        }
+        "noiseSeed" => {
+            let [seed] = args.numbers()?;
+            noise_seed(seed as u64)
+        }
+        "noiseDetail" => {
+            let [octaves, falloff] = args.numbers()?;
+            noise_detail(octaves as u32, falloff)
+        }
+        "print" | "println" => {
+            let words: Vec<_> = values.iter().map(Value::to_string).collect();
+            info!("{}", words.join(" "));
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// The arguments of a built-in call, with errors that name the function.
+#[derive(Clone, Copy)]
+struct Args<'a> {
+    name: &'a str,
+    values: &'a [Value],
+}
+
+impl<'a> Args<'a> {
+    fn usage(&self, expected: &str) -> String {
+        let found: Vec<_> = self.values.iter().map(Value::type_name).collect();
+        let found = if found.is_empty() {
+            "nothing".to_owned()
+        } else {
+            found.join(", ")
+        };
+        format!("`{}` takes {expected}, but was given {found}", self.name)
+    }
+
+    /// The first `n` arguments, and the rest.
+    fn split(self, n: usize) -> (Args<'a>, Args<'a>) {
+        let (first, rest) = self.values.split_at(n.min(self.values.len()));
+        (
+            Args {
+                name: self.name,
+                values: first,
+            },
+            Args {
+                name: self.name,
+                values: rest,
+            },
+        )
+    }
+
+    fn none(&self) -> Result<(), String> {
+        if self.values.is_empty() {
+            Ok(())
+        } else {
+            Err(self.usage("no arguments"))
+        }
+    }
+
+    fn all_numbers(&self) -> Result<Vec<f32>, String> {
+        self.values
+            .iter()
+            .map(|value| match value {
+                Value::Number(n) => Ok(*n as f32),
+                _ => Err(()),
+            })
+            .collect::<Result<_, _>>()
+            .map_err(|_| self.usage("numbers"))
+    }
+
+    fn numbers<const N: usize>(&self) -> Result<[f32; N], String> {
+        let expected = || match N {
+            1 => "a number".to_owned(),
+            n => format!("{n} numbers"),
+        };
+        let numbers = self.all_numbers().map_err(|_| self.usage(&expected()))?;
+        numbers.try_into().map_err(|_| self.usage(&expected()))
+    }
+
+    /// Up to `N` numbers, with zeros for the ones left out.
+    fn padded<const N: usize>(&self) -> Result<[f32; N], String> {
+        let mut numbers = self.all_numbers()?;
+        numbers.resize(N, 0.0);
+        numbers
+            .try_into()
+            .map_err(|_| self.usage(&format!("up to {N} numbers")))
+    }
+
+    /// A color from a color value, a hex string, or one to four numbers read
+    /// in the current color mode.
+    fn color(&self) -> Result<Color, String> {
+        let expected = "a color, a \"#rrggbb\" string or 1 to 4 numbers";
+        match self.values {
+            [Value::Color(c)] => Ok(*c),
+            [Value::Str(hex)] => hex_color(hex).ok_or_else(|| self.usage(expected)),
+            _ => match self.all_numbers().map_err(|_| self.usage(expected))?[..] {
+                [v] => Ok(gray(v)),
+                [v, a] => Ok(gray_alpha(v, a)),
+                [v1, v2, v3] => Ok(color(v1, v2, v3)),
+                [v1, v2, v3, a] => Ok(color_alpha(v1, v2, v3, a)),
+                _ => Err(self.usage(expected)),
+            },
+        }
+    }
+
+    /// Exactly one constant out of `options`.
+    fn constant<T: Copy>(&self, options: &[(&str, T)]) -> Result<T, String> {
+        let names: Vec<_> = options.iter().map(|(name, _)| *name).collect();
+        let expected = names.join(" or ");
+        let [Value::Str(given)] = self.values else {
+            return Err(self.usage(&expected));
+        };
+        options
+            .iter()
+            .find(|(name, _)| **name == **given)
+            .map(|(_, value)| *value)
+            .ok_or_else(|| self.usage(&expected))
+    }
+
+    /// Like `constant`, but `default` when no argument is given.
+    fn constant_or<T: Copy>(&self, default: T, options: &[(&str, T)]) -> Result<T, String> {
+        if self.values.is_empty() {
+            Ok(default)
+        } else {
+            self.constant(options)
+        }
+    }
+}
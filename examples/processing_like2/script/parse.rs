@@ -0,0 +1,1136 @@
+//! Turns script source into a syntax tree: a hand-written lexer and a
+//! recursive-descent parser, both recording where every token came from.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::ScriptError;
+
+/// A 1-based line and column in the script's source.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Pos {
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum TokenKind {
+    Number(f64),
+    Str(String),
+    Ident(String),
+    Punct(&'static str),
+    End,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    pos: Pos,
+}
+
+/// Longest first, so `+=` isn't read as `+` and `=`.
+const PUNCTUATION: &[&str] = &[
+    "===", "!==", "==", "!=", "<=", ">=", "&&", "||", "++", "--", "+=", "-=", "*=", "/=", "%=",
+    "+", "-", "*", "/", "%", "=", "<", ">", "!", "(", ")", "{", "}", "[", "]", ",", ";", ".", "?",
+    ":",
+];
+
+/// How deeply brackets, blocks, unary operators and chains of binary
+/// operators may nest, well before parsing the script could overflow the stack.
+const NESTING_LIMIT: u32 = 100;
+
+/// Words that declare a variable, a parameter or a function's return type.
+/// Types are accepted so Processing code reads naturally, but not checked.
+const TYPES: &[&str] = &[
+    "let", "var", "const", "float", "int", "boolean", "String", "color", "void",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ScriptError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let (mut i, mut line, mut column) = (0, 1, 1);
+    // Moves past `n` characters, keeping track of lines.
+    let advance = |i: &mut usize, line: &mut u32, column: &mut u32, n: usize| {
+        for _ in 0..n {
+            if chars[*i] == '\n' {
+                *line += 1;
+                *column = 1;
+            } else {
+                *column += 1;
+            }
+            *i += 1;
+        }
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        let pos = Pos { line, column };
+        let rest = &chars[i..];
+        if c.is_whitespace() {
+            advance(&mut i, &mut line, &mut column, 1);
+        } else if rest.starts_with(&['/', '/']) {
+            let len = rest.iter().position(|&c| c == '\n').unwrap_or(rest.len());
+            advance(&mut i, &mut line, &mut column, len);
+        } else if rest.starts_with(&['/', '*']) {
+            let Some(len) = rest.windows(2).position(|w| w == ['*', '/']) else {
+                return Err(ScriptError::new(pos, "unterminated /* comment"));
+            };
+            advance(&mut i, &mut line, &mut column, len + 2);
+        } else if c.is_ascii_digit() || (c == '.' && rest.get(1).is_some_and(char::is_ascii_digit))
+        {
+            let mut len = rest
+                .iter()
+                .position(|c| !(c.is_ascii_digit() || *c == '.'))
+                .unwrap_or(rest.len());
+            // An exponent, as in 1e-3.
+            if matches!(rest.get(len), Some('e' | 'E')) {
+                let sign = usize::from(matches!(rest.get(len + 1), Some('+' | '-')));
+                if rest.get(len + 1 + sign).is_some_and(char::is_ascii_digit) {
+                    len += 1 + sign;
+                    len += rest[len..]
+                        .iter()
+                        .take_while(|c| c.is_ascii_digit())
+                        .count();
+                }
+            }
+            let text: String = rest[..len].iter().collect();
+            let value = text
+                .parse()
+                .map_err(|_| ScriptError::new(pos, format!("`{text}` is not a number")))?;
+            tokens.push(Token {
+                kind: TokenKind::Number(value),
+                pos,
+            });
+            advance(&mut i, &mut line, &mut column, len);
+        } else if c == '"' || c == '\'' {
+            let mut text = String::new();
+            let mut len = 1;
+            loop {
+                match rest.get(len) {
+                    Some(&end) if end == c => break,
+                    Some('\\') => {
+                        text.push(match rest.get(len + 1) {
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some(&escaped @ ('\\' | '"' | '\'')) => escaped,
+                            _ => {
+                                return Err(ScriptError::new(pos, "unknown escape in string"));
+                            }
+                        });
+                        len += 2;
+                    }
+                    Some('\n') | None => {
+                        return Err(ScriptError::new(pos, "unterminated string"));
+                    }
+                    Some(&c) => {
+                        text.push(c);
+                        len += 1;
+                    }
+                }
+            }
+            tokens.push(Token {
+                kind: TokenKind::Str(text),
+                pos,
+            });
+            advance(&mut i, &mut line, &mut column, len + 1);
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let len = rest
+                .iter()
+                .position(|c| !(c.is_alphanumeric() || *c == '_' || *c == '$'))
+                .unwrap_or(rest.len());
+            tokens.push(Token {
+                kind: TokenKind::Ident(rest[..len].iter().collect()),
+                pos,
+            });
+            advance(&mut i, &mut line, &mut column, len);
+        } else if let Some(punct) = PUNCTUATION
+            .iter()
+            .find(|p| rest.iter().take(p.len()).copied().eq(p.chars()))
+        {
+            tokens.push(Token {
+                kind: TokenKind::Punct(punct),
+                pos,
+            });
+            advance(&mut i, &mut line, &mut column, punct.len());
+        } else {
+            return Err(ScriptError::new(pos, format!("unexpected character `{c}`")));
+        }
+    }
+    tokens.push(Token {
+        kind: TokenKind::End,
+        pos: Pos { line, column },
+    });
+    Ok(tokens)
+}
+
+/// A parsed script: its top-level statements, run once, and its functions.
+pub struct Program {
+    pub statements: Vec<Stmt>,
+    pub functions: HashMap<String, Arc<Function>>,
+}
+
+pub struct Function {
+    pub params: Vec<String>,
+    pub body: Vec<Stmt>,
+}
+
+pub enum Stmt {
+    /// `let a = 1, b;` and friends; a missing value starts as `null`.
+    Declare(Vec<(String, Option<Expr>)>),
+    Expr(Expr),
+    Block(Vec<Stmt>),
+    If {
+        condition: Expr,
+        then: Box<Stmt>,
+        otherwise: Option<Box<Stmt>>,
+    },
+    While {
+        condition: Expr,
+        body: Box<Stmt>,
+        pos: Pos,
+    },
+    For {
+        init: Option<Box<Stmt>>,
+        condition: Option<Expr>,
+        step: Option<Expr>,
+        body: Box<Stmt>,
+        pos: Pos,
+    },
+    Return(Option<Expr>),
+    Break(Pos),
+    Continue(Pos),
+}
+
+pub struct Expr {
+    pub kind: ExprKind,
+    pub pos: Pos,
+}
+
+pub enum ExprKind {
+    Number(f64),
+    Str(Arc<str>),
+    Bool(bool),
+    Null,
+    Array(Vec<Expr>),
+    Variable(String),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// `&&` when `true`, `||` when `false`; only evaluates the right side if needed.
+    Logical(bool, Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// `target = value`, or `target op= value` when `op` is set.
+    Assign {
+        target: Box<Expr>,
+        op: Option<BinaryOp>,
+        value: Box<Expr>,
+    },
+    /// `++`/`--`, giving the new value if `prefix` and the old one otherwise.
+    Increment {
+        target: Box<Expr>,
+        delta: f64,
+        prefix: bool,
+    },
+    Call(String, Vec<Expr>),
+    Method(Box<Expr>, String, Vec<Expr>),
+    Index(Box<Expr>, Box<Expr>),
+    Member(Box<Expr>, String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl BinaryOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Remainder => "%",
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+        }
+    }
+}
+
+pub fn parse(source: &str) -> Result<Program, ScriptError> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        next: 0,
+        depth: 0,
+    };
+    let mut statements = Vec::new();
+    let mut functions = HashMap::new();
+    while !parser.at_end() {
+        if let Some((name, pos, function)) = parser.function()? {
+            if functions.insert(name.clone(), Arc::new(function)).is_some() {
+                return Err(ScriptError::new(
+                    pos,
+                    format!("function `{name}` is declared twice"),
+                ));
+            }
+        } else {
+            statements.push(parser.statement()?);
+        }
+    }
+    Ok(Program {
+        statements,
+        functions,
+    })
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+    /// How deeply the syntax tree is nested at the next token.
+    depth: u32,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next]
+    }
+
+    fn peek_at(&self, offset: usize) -> &TokenKind {
+        let last = self.tokens.len() - 1;
+        &self.tokens[(self.next + offset).min(last)].kind
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.next].clone();
+        if token.kind != TokenKind::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn at_end(&self) -> bool {
+        self.peek().kind == TokenKind::End
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        matches!(self.peek().kind, TokenKind::Punct(p) if p == punct)
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        matches!(&self.peek().kind, TokenKind::Ident(w) if w == word)
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        let found = self.is_punct(punct);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let found = self.is_word(word);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn unexpected(&self, expected: &str) -> ScriptError {
+        let token = self.peek();
+        let found = match &token.kind {
+            TokenKind::Number(n) => format!("`{n}`"),
+            TokenKind::Str(s) => format!("\"{s}\""),
+            TokenKind::Ident(name) => format!("`{name}`"),
+            TokenKind::Punct(p) => format!("`{p}`"),
+            TokenKind::End => "the end of the script".into(),
+        };
+        ScriptError::new(token.pos, format!("expected {expected}, found {found}"))
+    }
+
+    /// Goes one level deeper into the syntax tree, failing past `NESTING_LIMIT`.
+    /// Callers put `depth` back once they've parsed what's nested.
+    fn enter(&mut self) -> Result<(), ScriptError> {
+        self.depth += 1;
+        if self.depth > NESTING_LIMIT {
+            return Err(ScriptError::new(
+                self.peek().pos,
+                format!("nested more than {NESTING_LIMIT} deep"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Runs `parse` one level deeper into the syntax tree.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ScriptError>,
+    ) -> Result<T, ScriptError> {
+        let depth = self.depth;
+        self.enter()?;
+        let result = parse(self);
+        self.depth = depth;
+        result
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<(), ScriptError> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{punct}`")))
+        }
+    }
+
+    fn name(&mut self) -> Result<(String, Pos), ScriptError> {
+        match &self.peek().kind {
+            TokenKind::Ident(name) if !is_reserved(name) => {
+                let name = name.clone();
+                Ok((name, self.advance().pos))
+            }
+            _ => Err(self.unexpected("a name")),
+        }
+    }
+
+    /// `function f(a, b) { ... }` or Processing's `float f(float a, float b) { ... }`.
+    fn function(&mut self) -> Result<Option<(String, Pos, Function)>, ScriptError> {
+        let is_function = match self.peek_at(0) {
+            TokenKind::Ident(word) if word == "function" => true,
+            TokenKind::Ident(word) if TYPES.contains(&word.as_str()) => {
+                matches!(self.peek_at(1), TokenKind::Ident(_))
+                    && *self.peek_at(2) == TokenKind::Punct("(")
+            }
+            _ => false,
+        };
+        if !is_function {
+            return Ok(None);
+        }
+        self.advance();
+        let (name, pos) = self.name()?;
+        self.expect_punct("(")?;
+        let mut params = Vec::new();
+        while !self.eat_punct(")") {
+            if !params.is_empty() {
+                self.expect_punct(",")?;
+            }
+            self.skip_type();
+            params.push(self.name()?.0);
+        }
+        let body = self.block()?;
+        Ok(Some((name, pos, Function { params, body })))
+    }
+
+    /// Skips a declaration word, if there is one and a name follows it.
+    fn skip_type(&mut self) -> bool {
+        let typed = matches!(self.peek_at(0), TokenKind::Ident(word) if TYPES.contains(&word.as_str()))
+            && matches!(self.peek_at(1), TokenKind::Ident(_));
+        if typed {
+            self.next += 1;
+        }
+        typed
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, ScriptError> {
+        self.expect_punct("{")?;
+        let mut statements = Vec::new();
+        while !self.eat_punct("}") {
+            if self.at_end() {
+                return Err(self.unexpected("`}`"));
+            }
+            statements.push(self.statement()?);
+        }
+        Ok(statements)
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ScriptError> {
+        self.nested(Self::unnested_statement)
+    }
+
+    fn unnested_statement(&mut self) -> Result<Stmt, ScriptError> {
+        let pos = self.peek().pos;
+        if self.is_punct("{") {
+            return Ok(Stmt::Block(self.block()?));
+        }
+        if self.eat_word("if") {
+            self.expect_punct("(")?;
+            let condition = self.expression()?;
+            self.expect_punct(")")?;
+            let then = Box::new(self.statement()?);
+            let otherwise = if self.eat_word("else") {
+                Some(Box::new(self.statement()?))
+            } else {
+                None
+            };
+            return Ok(Stmt::If {
+                condition,
+                then,
+                otherwise,
+            });
+        }
+        if self.eat_word("while") {
+            self.expect_punct("(")?;
+            let condition = self.expression()?;
+            self.expect_punct(")")?;
+            let body = Box::new(self.statement()?);
+            return Ok(Stmt::While {
+                condition,
+                body,
+                pos,
+            });
+        }
+        if self.eat_word("for") {
+            self.expect_punct("(")?;
+            let init = if self.eat_punct(";") {
+                None
+            } else {
+                Some(Box::new(self.simple_statement()?))
+            };
+            let condition = if self.is_punct(";") {
+                None
+            } else {
+                Some(self.expression()?)
+            };
+            self.expect_punct(";")?;
+            let step = if self.is_punct(")") {
+                None
+            } else {
+                Some(self.expression()?)
+            };
+            self.expect_punct(")")?;
+            let body = Box::new(self.statement()?);
+            return Ok(Stmt::For {
+                init,
+                condition,
+                step,
+                body,
+                pos,
+            });
+        }
+        if self.eat_word("return") {
+            let value = if self.is_punct(";") || self.is_punct("}") {
+                None
+            } else {
+                Some(self.expression()?)
+            };
+            self.eat_punct(";");
+            return Ok(Stmt::Return(value));
+        }
+        if self.eat_word("break") {
+            self.eat_punct(";");
+            return Ok(Stmt::Break(pos));
+        }
+        if self.eat_word("continue") {
+            self.eat_punct(";");
+            return Ok(Stmt::Continue(pos));
+        }
+        if self.is_word("function") {
+            return Err(ScriptError::new(
+                pos,
+                "functions can only be declared at the top level",
+            ));
+        }
+        self.simple_statement()
+    }
+
+    /// A declaration or expression, ended by `;`. Semicolons before a newline
+    /// are optional, as in JavaScript.
+    fn simple_statement(&mut self) -> Result<Stmt, ScriptError> {
+        let statement = if self.skip_type() {
+            let mut names = Vec::new();
+            loop {
+                let (name, _) = self.name()?;
+                let value = if self.eat_punct("=") {
+                    Some(self.expression()?)
+                } else {
+                    None
+                };
+                names.push((name, value));
+                if !self.eat_punct(",") {
+                    break;
+                }
+            }
+            Stmt::Declare(names)
+        } else {
+            Stmt::Expr(self.expression()?)
+        };
+        let previous_line = self.tokens[self.next.saturating_sub(1)].pos.line;
+        if !self.eat_punct(";")
+            && !self.is_punct("}")
+            && !self.at_end()
+            && self.peek().pos.line == previous_line
+        {
+            return Err(self.unexpected("`;`"));
+        }
+        Ok(statement)
+    }
+
+    fn expression(&mut self) -> Result<Expr, ScriptError> {
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> Result<Expr, ScriptError> {
+        self.nested(Self::unnested_assignment)
+    }
+
+    fn unnested_assignment(&mut self) -> Result<Expr, ScriptError> {
+        let target = self.conditional()?;
+        let op = match self.peek().kind {
+            TokenKind::Punct("=") => None,
+            TokenKind::Punct("+=") => Some(BinaryOp::Add),
+            TokenKind::Punct("-=") => Some(BinaryOp::Subtract),
+            TokenKind::Punct("*=") => Some(BinaryOp::Multiply),
+            TokenKind::Punct("/=") => Some(BinaryOp::Divide),
+            TokenKind::Punct("%=") => Some(BinaryOp::Remainder),
+            _ => return Ok(target),
+        };
+        let pos = self.advance().pos;
+        if !is_place(&target) {
+            return Err(ScriptError::new(
+                pos,
+                "can only assign to a variable or element",
+            ));
+        }
+        let value = self.assignment()?;
+        Ok(Expr {
+            pos,
+            kind: ExprKind::Assign {
+                target: Box::new(target),
+                op,
+                value: Box::new(value),
+            },
+        })
+    }
+
+    fn conditional(&mut self) -> Result<Expr, ScriptError> {
+        let condition = self.logical(false)?;
+        if !self.is_punct("?") {
+            return Ok(condition);
+        }
+        let pos = self.advance().pos;
+        let then = self.assignment()?;
+        self.expect_punct(":")?;
+        let otherwise = self.assignment()?;
+        Ok(Expr {
+            pos,
+            kind: ExprKind::Conditional(Box::new(condition), Box::new(then), Box::new(otherwise)),
+        })
+    }
+
+    /// `||` binds looser than `&&`, so `and` parses the operands of `or`.
+    fn logical(&mut self, and: bool) -> Result<Expr, ScriptError> {
+        let operator = if and { "&&" } else { "||" };
+        let depth = self.depth;
+        let mut left = if and {
+            self.binary(0)?
+        } else {
+            self.logical(true)?
+        };
+        while self.is_punct(operator) {
+            // Each operator nests the chain so far one level deeper.
+            self.enter()?;
+            let pos = self.advance().pos;
+            let right = if and {
+                self.binary(0)?
+            } else {
+                self.logical(true)?
+            };
+            left = Expr {
+                pos,
+                kind: ExprKind::Logical(and, Box::new(left), Box::new(right)),
+            };
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    /// Binary operators from `level` upwards, loosest first.
+    fn binary(&mut self, level: usize) -> Result<Expr, ScriptError> {
+        const LEVELS: &[&[(&str, BinaryOp)]] = &[
+            &[
+                ("==", BinaryOp::Equal),
+                ("===", BinaryOp::Equal),
+                ("!=", BinaryOp::NotEqual),
+                ("!==", BinaryOp::NotEqual),
+            ],
+            &[
+                ("<", BinaryOp::Less),
+                ("<=", BinaryOp::LessEqual),
+                (">", BinaryOp::Greater),
+                (">=", BinaryOp::GreaterEqual),
+            ],
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Subtract)],
+            &[
+                ("*", BinaryOp::Multiply),
+                ("/", BinaryOp::Divide),
+                ("%", BinaryOp::Remainder),
+            ],
+        ];
+        let Some(operators) = LEVELS.get(level) else {
+            return self.unary();
+        };
+        let depth = self.depth;
+        let mut left = self.binary(level + 1)?;
+        while let TokenKind::Punct(punct) = self.peek().kind
+            && let Some(&(_, op)) = operators.iter().find(|(p, _)| *p == punct)
+        {
+            self.enter()?;
+            let pos = self.advance().pos;
+            let right = self.binary(level + 1)?;
+            left = Expr {
+                pos,
+                kind: ExprKind::Binary(op, Box::new(left), Box::new(right)),
+            };
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ScriptError> {
+        self.nested(Self::unnested_unary)
+    }
+
+    fn unnested_unary(&mut self) -> Result<Expr, ScriptError> {
+        let pos = self.peek().pos;
+        if self.eat_punct("-") {
+            let operand = self.unary()?;
+            return Ok(Expr {
+                pos,
+                kind: ExprKind::Negate(Box::new(operand)),
+            });
+        }
+        if self.eat_punct("+") {
+            return self.unary();
+        }
+        if self.eat_punct("!") {
+            let operand = self.unary()?;
+            return Ok(Expr {
+                pos,
+                kind: ExprKind::Not(Box::new(operand)),
+            });
+        }
+        if self.is_punct("++") || self.is_punct("--") {
+            let delta = if self.advance().kind == TokenKind::Punct("++") {
+                1.0
+            } else {
+                -1.0
+            };
+            let target = self.unary()?;
+            return increment(target, delta, true, pos);
+        }
+        let mut expr = self.postfix()?;
+        while self.is_punct("++") || self.is_punct("--") {
+            self.enter()?;
+            let token = self.advance();
+            let delta = if token.kind == TokenKind::Punct("++") {
+                1.0
+            } else {
+                -1.0
+            };
+            expr = increment(expr, delta, false, token.pos)?;
+        }
+        Ok(expr)
+    }
+
+    /// A primary expression followed by any number of calls, `[index]`es and `.member`s.
+    fn postfix(&mut self) -> Result<Expr, ScriptError> {
+        let depth = self.depth;
+        let mut expr = self.primary()?;
+        loop {
+            let pos = self.peek().pos;
+            if self.is_punct("(") || self.is_punct("[") || self.is_punct(".") {
+                self.enter()?;
+            }
+            if self.is_punct("(") {
+                let ExprKind::Variable(name) = expr.kind else {
+                    return Err(ScriptError::new(pos, "only named functions can be called"));
+                };
+                let args = self.arguments()?;
+                expr = Expr {
+                    pos: expr.pos,
+                    kind: ExprKind::Call(name, args),
+                };
+            } else if self.eat_punct("[") {
+                let index = self.expression()?;
+                self.expect_punct("]")?;
+                expr = Expr {
+                    pos,
+                    kind: ExprKind::Index(Box::new(expr), Box::new(index)),
+                };
+            } else if self.eat_punct(".") {
+                let (name, pos) = self.name()?;
+                expr = if self.is_punct("(") {
+                    let args = self.arguments()?;
+                    Expr {
+                        pos,
+                        kind: ExprKind::Method(Box::new(expr), name, args),
+                    }
+                } else {
+                    Expr {
+                        pos,
+                        kind: ExprKind::Member(Box::new(expr), name),
+                    }
+                };
+            } else {
+                self.depth = depth;
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Expr>, ScriptError> {
+        self.expect_punct("(")?;
+        let mut args = Vec::new();
+        while !self.eat_punct(")") {
+            if !args.is_empty() {
+                self.expect_punct(",")?;
+            }
+            args.push(self.expression()?);
+        }
+        Ok(args)
+    }
+
+    fn primary(&mut self) -> Result<Expr, ScriptError> {
+        let token = self.peek().clone();
+        let kind = match token.kind {
+            TokenKind::Number(value) => ExprKind::Number(value),
+            TokenKind::Str(text) => ExprKind::Str(text.into()),
+            TokenKind::Ident(word) => match word.as_str() {
+                "true" => ExprKind::Bool(true),
+                "false" => ExprKind::Bool(false),
+                "null" => ExprKind::Null,
+                // `color(...)`, `int(...)` and `float(...)` are functions as well as types.
+                _ if TYPES.contains(&word.as_str())
+                    && *self.peek_at(1) == TokenKind::Punct("(") =>
+                {
+                    ExprKind::Variable(word)
+                }
+                _ => ExprKind::Variable(self.name()?.0),
+            },
+            TokenKind::Punct("(") => {
+                self.advance();
+                let expr = self.expression()?;
+                self.expect_punct(")")?;
+                return Ok(expr);
+            }
+            TokenKind::Punct("[") => {
+                self.advance();
+                let mut items = Vec::new();
+                while !self.eat_punct("]") {
+                    if !items.is_empty() {
+                        self.expect_punct(",")?;
+                        // Allow a trailing comma.
+                        if self.eat_punct("]") {
+                            break;
+                        }
+                    }
+                    items.push(self.expression()?);
+                }
+                return Ok(Expr {
+                    pos: token.pos,
+                    kind: ExprKind::Array(items),
+                });
+            }
+            _ => return Err(self.unexpected("an expression")),
+        };
+        if !matches!(&kind, ExprKind::Variable(name) if !TYPES.contains(&name.as_str())) {
+            self.advance();
+        }
+        Ok(Expr {
+            pos: token.pos,
+            kind,
+        })
+    }
+}
+
+fn is_reserved(word: &str) -> bool {
+    TYPES.contains(&word)
+        || matches!(
+            word,
+            "function"
+                | "if"
+                | "else"
+                | "while"
+                | "for"
+                | "return"
+                | "break"
+                | "continue"
+                | "true"
+                | "false"
+                | "null"
+        )
+}
+
+/// Whether `expr` names something that can be assigned to.
+fn is_place(expr: &Expr) -> bool {
+    matches!(expr.kind, ExprKind::Variable(_) | ExprKind::Index(..))
+}
+
+fn increment(target: Expr, delta: f64, prefix: bool, pos: Pos) -> Result<Expr, ScriptError> {
+    if !is_place(&target) {
+        return Err(ScriptError::new(
+            pos,
+            "can only increment a variable or element",
+        ));
+    }
+    Ok(Expr {
+        pos,
+        kind: ExprKind::Increment {
+            target: Box::new(target),
+            delta,
+            prefix,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(line: u32, column: u32) -> Pos {
+        Pos { line, column }
+    }
+
+    fn tokens(source: &str) -> Vec<(TokenKind, Pos)> {
+        tokenize(source)
+            .unwrap()
+            .into_iter()
+            .map(|token| (token.kind, token.pos))
+            .collect()
+    }
+
+    /// The position and message of the error parsing `source` fails with.
+    fn error(source: &str) -> (Pos, String) {
+        match parse(source) {
+            Ok(_) => panic!("`{source}` parsed"),
+            Err(error) => (error.pos, error.message),
+        }
+    }
+
+    /// Writes an expression out with every operation in brackets.
+    fn show(expr: &Expr) -> String {
+        let list = |exprs: &[Expr]| exprs.iter().map(show).collect::<Vec<_>>().join(", ");
+        match &expr.kind {
+            ExprKind::Number(n) => n.to_string(),
+            ExprKind::Str(s) => format!("{s:?}"),
+            ExprKind::Bool(b) => b.to_string(),
+            ExprKind::Null => "null".into(),
+            ExprKind::Array(items) => format!("[{}]", list(items)),
+            ExprKind::Variable(name) => name.clone(),
+            ExprKind::Negate(operand) => format!("(-{})", show(operand)),
+            ExprKind::Not(operand) => format!("(!{})", show(operand)),
+            ExprKind::Binary(op, left, right) => {
+                format!("({} {} {})", show(left), op.symbol(), show(right))
+            }
+            ExprKind::Logical(and, left, right) => {
+                let op = if *and { "&&" } else { "||" };
+                format!("({} {op} {})", show(left), show(right))
+            }
+            ExprKind::Conditional(condition, then, otherwise) => {
+                format!(
+                    "({} ? {} : {})",
+                    show(condition),
+                    show(then),
+                    show(otherwise)
+                )
+            }
+            ExprKind::Assign { target, op, value } => {
+                let op = op.map_or("", BinaryOp::symbol);
+                format!("({} {op}= {})", show(target), show(value))
+            }
+            ExprKind::Increment {
+                target,
+                delta,
+                prefix,
+            } => {
+                let op = if *delta > 0.0 { "++" } else { "--" };
+                if *prefix {
+                    format!("({op}{})", show(target))
+                } else {
+                    format!("({}{op})", show(target))
+                }
+            }
+            ExprKind::Call(name, args) => format!("{name}({})", list(args)),
+            ExprKind::Method(object, name, args) => {
+                format!("{}.{name}({})", show(object), list(args))
+            }
+            ExprKind::Index(object, index) => format!("{}[{}]", show(object), show(index)),
+            ExprKind::Member(object, name) => format!("{}.{name}", show(object)),
+        }
+    }
+
+    fn expression(source: &str) -> String {
+        let program = parse(source).unwrap();
+        match &program.statements[..] {
+            [Stmt::Expr(expr)] => show(expr),
+            _ => panic!("`{source}` isn't one expression"),
+        }
+    }
+
+    #[test]
+    fn tokens_know_their_line_and_column() {
+        let source = "let x = 1.5e3; // one\n  /* two\n */ x += \"a\\n\" ;";
+        assert_eq!(
+            tokens(source),
+            [
+                (TokenKind::Ident("let".into()), pos(1, 1)),
+                (TokenKind::Ident("x".into()), pos(1, 5)),
+                (TokenKind::Punct("="), pos(1, 7)),
+                (TokenKind::Number(1500.0), pos(1, 9)),
+                (TokenKind::Punct(";"), pos(1, 14)),
+                (TokenKind::Ident("x".into()), pos(3, 5)),
+                (TokenKind::Punct("+="), pos(3, 7)),
+                (TokenKind::Str("a\n".into()), pos(3, 10)),
+                (TokenKind::Punct(";"), pos(3, 16)),
+                (TokenKind::End, pos(3, 17)),
+            ]
+        );
+    }
+
+    #[test]
+    fn longest_punctuation_wins() {
+        let kinds: Vec<_> = tokens("a===b!=c<=-d").into_iter().map(|t| t.0).collect();
+        assert_eq!(
+            kinds[1..kinds.len() - 1]
+                .iter()
+                .filter(|kind| matches!(kind, TokenKind::Punct(_)))
+                .collect::<Vec<_>>(),
+            [
+                &TokenKind::Punct("==="),
+                &TokenKind::Punct("!="),
+                &TokenKind::Punct("<="),
+                &TokenKind::Punct("-"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenizer_errors() {
+        assert_eq!(
+            error("let s = \"abc\nx"),
+            (pos(1, 9), "unterminated string".into())
+        );
+        assert_eq!(
+            error("x = 1;\n  y = 'a\\q'"),
+            (pos(2, 7), "unknown escape in string".into())
+        );
+        assert_eq!(
+            error("x = 1 /* never\nclosed"),
+            (pos(1, 7), "unterminated /* comment".into())
+        );
+        assert_eq!(
+            error("x = 1;\ny = 2 # 3"),
+            (pos(2, 7), "unexpected character `#`".into())
+        );
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(
+            expression("a = b += 1 + 2 * -c % 3"),
+            "(a = (b += (1 + ((2 * (-c)) % 3))))"
+        );
+        assert_eq!(
+            expression("a || b && !c == d < e"),
+            "(a || (b && ((!c) == (d < e))))"
+        );
+        assert_eq!(expression("a ? b : c ? d : e"), "(a ? b : (c ? d : e))");
+        assert_eq!(expression("1 - 2 - 3"), "((1 - 2) - 3)");
+        assert_eq!(expression("-x++ + ++y[0]"), "((-(x++)) + (++y[0]))");
+        assert_eq!(
+            expression("a.b.c(1, [2, 3,])[4].length"),
+            "a.b.c(1, [2, 3])[4].length"
+        );
+    }
+
+    #[test]
+    fn declarations_and_functions() {
+        let program = parse(
+            "float x = 1, y;\nvoid draw() { int i = 0; }\nfunction f(float a, b) { return a; }",
+        )
+        .unwrap();
+        assert!(matches!(&program.statements[..], [Stmt::Declare(names)] if names.len() == 2));
+        assert_eq!(program.functions["draw"].params.len(), 0);
+        assert_eq!(program.functions["f"].params, ["a", "b"]);
+        // `color(...)` is a call even though `color` declares variables too.
+        assert_eq!(expression("color(255)"), "color(255)");
+    }
+
+    #[test]
+    fn semicolons_are_optional_at_the_end_of_a_line() {
+        assert!(parse("x = 1\ny = 2").is_ok());
+        assert_eq!(
+            error("x = 1 y = 2"),
+            (pos(1, 7), "expected `;`, found `y`".into())
+        );
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_problem() {
+        assert_eq!(
+            error("let a = 1\nlet b = (2 + ;"),
+            (pos(2, 14), "expected an expression, found `;`".into())
+        );
+        assert_eq!(
+            error("function f() {\n  x = 1;"),
+            (
+                pos(2, 9),
+                "expected `}`, found the end of the script".into()
+            )
+        );
+        assert_eq!(
+            error("function f() {}\nvoid f() {}"),
+            (pos(2, 6), "function `f` is declared twice".into())
+        );
+        assert_eq!(
+            error("if (x) {\n  function g() {}\n}"),
+            (
+                pos(2, 3),
+                "functions can only be declared at the top level".into()
+            )
+        );
+        assert_eq!(
+            error("1 = x;"),
+            (pos(1, 3), "can only assign to a variable or element".into())
+        );
+        assert_eq!(
+            error("let if = 1;"),
+            (pos(1, 5), "expected a name, found `if`".into())
+        );
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let brackets = |n| format!("x = {}1{};", "(".repeat(n), ")".repeat(n));
+        assert!(parse(&brackets(45)).is_ok());
+        let (at, message) = error(&brackets(1000));
+        assert_eq!(message, format!("nested more than {NESTING_LIMIT} deep"));
+        assert_eq!(at.line, 1);
+
+        assert!(parse(&format!("x = {}1;", "!".repeat(90))).is_ok());
+        assert!(
+            error(&format!("x = {}1;", "!".repeat(100_000)))
+                .1
+                .starts_with("nested")
+        );
+
+        let sum = |n| format!("x = 1{};", " + 1".repeat(n));
+        assert!(parse(&sum(90)).is_ok());
+        assert!(error(&sum(100_000)).1.starts_with("nested"));
+        assert!(
+            error(&format!("{}x = 1;", "{".repeat(1000)))
+                .1
+                .starts_with("nested")
+        );
+    }
+}
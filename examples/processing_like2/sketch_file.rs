@@ -0,0 +1,772 @@
+//! Sketches kept in asset files instead of Rust: one sketch call per line,
+//! loaded through the `AssetServer` and replayed on every frame. With Bevy's
+//! `file_watcher` feature the file is reloaded whenever it is saved, so a
+//! composition can be tweaked while the example keeps running.
+//!
+//! ```text
+//! // A tilted yellow square on a dark background.
+//! background 26 26 31
+//! fill #ffcc00
+//! stroke 255
+//! stroke_weight 2
+//! translate 200 200
+//! rotate QUARTER_PI
+//! rect -50 -50 100 100
+//! text "hello" 200 380
+//! ```
+//!
+//! Calls are named like the Rust functions and take the same numbers. Colors
+//! are one to four numbers read in the current `color_mode` (as `gray()`,
+//! `gray_alpha()`, `color()` and `color_alpha()` would) or a `#rrggbb` hex
+//! code. Angles are radians and may use `PI`, `HALF_PI`, `QUARTER_PI` and
+//! `TWO_PI`. Enum arguments are written in snake case, e.g.
+//! `begin_shape triangle_strip` or `arc 200 200 80 80 0 PI pie`.
+
+use std::fmt;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+
+use super::blend::{BlendMode, blend_mode};
+use super::color::{
+    ColorMode, color, color_alpha, color_mode, color_mode_max, gray, gray_alpha, hex_color,
+};
+use super::image::{ImageMode, image, image_mode, image_sized, load_image, no_tint, tint};
+use super::stroke::{StrokeCap, StrokeJoin, miter_limit, stroke_cap, stroke_join};
+use super::text::{TextAlign, TextBaseline, load_font, text, text_align, text_font, text_size};
+use super::{
+    ArcMode, EndShape, ShapeKind, ShapeMode, SketchContext, arc, background, begin_contour,
+    begin_shape, bezier, bezier_vertex, circle, curve, curve_vertex, ellipse, ellipse_mode,
+    end_contour, end_shape, fill, layer, line, no_fill, no_stroke, point, pop_matrix, push_matrix,
+    quad, quadratic_vertex, rect_mode, rect_radii, reset_matrix, rotate, scale, size, square,
+    stroke, stroke_weight, translate, triangle, vertex,
+};
+
+/// A parsed sketch file.
+#[derive(Asset, TypePath, Clone)]
+pub struct SketchFile {
+    calls: Vec<Call>,
+}
+
+impl SketchFile {
+    /// Makes every call in the file, in order, on the current canvas.
+    pub fn replay(&self) {
+        for call in &self.calls {
+            call.make();
+        }
+    }
+}
+
+/// Draws a `SketchFile` on the `Canvas` it is attached to, every frame.
+#[derive(Component, Clone)]
+#[require(super::Canvas)]
+pub struct SketchSource(pub Handle<SketchFile>);
+
+/// A color argument, kept as written so it follows `color_mode` at draw time.
+#[derive(Clone, Debug)]
+enum Paint {
+    Gray(f32),
+    GrayAlpha(f32, f32),
+    Color(f32, f32, f32),
+    ColorAlpha(f32, f32, f32, f32),
+    Hex(Color),
+}
+
+impl Paint {
+    fn resolve(&self) -> Color {
+        match *self {
+            Paint::Gray(v) => gray(v),
+            Paint::GrayAlpha(v, a) => gray_alpha(v, a),
+            Paint::Color(a, b, c) => color(a, b, c),
+            Paint::ColorAlpha(a, b, c, alpha) => color_alpha(a, b, c, alpha),
+            Paint::Hex(c) => c,
+        }
+    }
+}
+
+/// One line of a sketch file.
+#[derive(Clone, Debug)]
+enum Call {
+    Size([f32; 2]),
+    Background(Paint),
+    Fill(Paint),
+    NoFill,
+    Stroke(Paint),
+    NoStroke,
+    StrokeWeight(f32),
+    StrokeCap(StrokeCap),
+    StrokeJoin(StrokeJoin),
+    MiterLimit(f32),
+    BlendMode(BlendMode),
+    Layer(i32),
+    ColorMode(ColorMode, Option<[f32; 4]>),
+    PushMatrix,
+    PopMatrix,
+    ResetMatrix,
+    Translate([f32; 2]),
+    Rotate(f32),
+    Scale([f32; 2]),
+    RectMode(ShapeMode),
+    EllipseMode(ShapeMode),
+    Point([f32; 2]),
+    Line([f32; 4]),
+    /// The rectangle, then its corner radii clockwise from the top left.
+    Rect([f32; 4], [f32; 4]),
+    Square([f32; 3]),
+    Ellipse([f32; 4]),
+    Circle([f32; 3]),
+    Triangle([f32; 6]),
+    Quad([f32; 8]),
+    Arc([f32; 6], ArcMode),
+    Bezier([f32; 8]),
+    Curve([f32; 8]),
+    BeginShape(ShapeKind),
+    Vertex([f32; 2]),
+    BezierVertex([f32; 6]),
+    QuadraticVertex([f32; 4]),
+    CurveVertex([f32; 2]),
+    BeginContour,
+    EndContour,
+    EndShape(EndShape),
+    TextFont(String),
+    TextSize(f32),
+    TextAlign(TextAlign, TextBaseline),
+    Text(String, [f32; 2]),
+    ImageMode(ImageMode),
+    Tint(Paint),
+    NoTint,
+    Image(String, [f32; 2], Option<[f32; 2]>),
+}
+
+impl Call {
+    fn make(&self) {
+        match self {
+            Call::Size([w, h]) => size(*w, *h),
+            Call::Background(paint) => background(paint.resolve()),
+            Call::Fill(paint) => fill(paint.resolve()),
+            Call::NoFill => no_fill(),
+            Call::Stroke(paint) => stroke(paint.resolve()),
+            Call::NoStroke => no_stroke(),
+            Call::StrokeWeight(weight) => stroke_weight(*weight),
+            Call::StrokeCap(cap) => stroke_cap(*cap),
+            Call::StrokeJoin(join) => stroke_join(*join),
+            Call::MiterLimit(limit) => miter_limit(*limit),
+            Call::BlendMode(mode) => blend_mode(*mode),
+            Call::Layer(n) => layer(*n),
+            Call::ColorMode(mode, None) => color_mode(*mode),
+            Call::ColorMode(mode, Some([a, b, c, alpha])) => {
+                color_mode_max(*mode, *a, *b, *c, *alpha)
+            }
+            Call::PushMatrix => push_matrix(),
+            Call::PopMatrix => pop_matrix(),
+            Call::ResetMatrix => reset_matrix(),
+            Call::Translate([x, y]) => translate(*x, *y),
+            Call::Rotate(angle) => rotate(*angle),
+            Call::Scale([sx, sy]) => scale(*sx, *sy),
+            Call::RectMode(mode) => rect_mode(*mode),
+            Call::EllipseMode(mode) => ellipse_mode(*mode),
+            Call::Point([x, y]) => point(*x, *y),
+            Call::Line([x1, y1, x2, y2]) => line(*x1, *y1, *x2, *y2),
+            Call::Rect([a, b, c, d], [tl, tr, br, bl]) => {
+                rect_radii(*a, *b, *c, *d, *tl, *tr, *br, *bl)
+            }
+            Call::Square([x, y, extent]) => square(*x, *y, *extent),
+            Call::Ellipse([a, b, c, d]) => ellipse(*a, *b, *c, *d),
+            Call::Circle([x, y, extent]) => circle(*x, *y, *extent),
+            Call::Triangle([x1, y1, x2, y2, x3, y3]) => triangle(*x1, *y1, *x2, *y2, *x3, *y3),
+            Call::Quad([x1, y1, x2, y2, x3, y3, x4, y4]) => {
+                quad(*x1, *y1, *x2, *y2, *x3, *y3, *x4, *y4)
+            }
+            Call::Arc([cx, cy, w, h, start, stop], mode) => {
+                arc(*cx, *cy, *w, *h, *start, *stop, *mode)
+            }
+            Call::Bezier([x1, y1, cx1, cy1, cx2, cy2, x2, y2]) => {
+                bezier(*x1, *y1, *cx1, *cy1, *cx2, *cy2, *x2, *y2)
+            }
+            Call::Curve([x1, y1, x2, y2, x3, y3, x4, y4]) => {
+                curve(*x1, *y1, *x2, *y2, *x3, *y3, *x4, *y4)
+            }
+            Call::BeginShape(kind) => begin_shape(*kind),
+            Call::Vertex([x, y]) => vertex(*x, *y),
+            Call::BezierVertex([cx1, cy1, cx2, cy2, x, y]) => {
+                bezier_vertex(*cx1, *cy1, *cx2, *cy2, *x, *y)
+            }
+            Call::QuadraticVertex([cx, cy, x, y]) => quadratic_vertex(*cx, *cy, *x, *y),
+            Call::CurveVertex([x, y]) => curve_vertex(*x, *y),
+            Call::BeginContour => begin_contour(),
+            Call::EndContour => end_contour(),
+            Call::EndShape(mode) => end_shape(*mode),
+            // Loading is cached by the asset server, so naming the file every
+            // frame costs a lookup rather than a reload.
+            Call::TextFont(path) => text_font(&load_font(path)),
+            Call::TextSize(size) => text_size(*size),
+            Call::TextAlign(align, baseline) => text_align(*align, *baseline),
+            Call::Text(content, [x, y]) => text(content.as_str(), *x, *y),
+            Call::ImageMode(mode) => image_mode(*mode),
+            Call::Tint(paint) => tint(paint.resolve()),
+            Call::NoTint => no_tint(),
+            Call::Image(path, [x, y], None) => image(&load_image(path), *x, *y),
+            Call::Image(path, [x, y], Some([w, h])) => {
+                image_sized(&load_image(path), *x, *y, *w, *h)
+            }
+        }
+    }
+}
+
+/// Why a sketch file failed to load.
+#[derive(Debug)]
+pub enum SketchFileError {
+    Io(std::io::Error),
+    /// A line that isn't a call, counted from 1.
+    Syntax {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for SketchFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SketchFileError::Io(error) => write!(f, "could not read sketch file: {error}"),
+            SketchFileError::Syntax { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for SketchFileError {}
+
+impl From<std::io::Error> for SketchFileError {
+    fn from(error: std::io::Error) -> Self {
+        SketchFileError::Io(error)
+    }
+}
+
+/// Loads `.sketch` files.
+#[derive(Default)]
+pub struct SketchFileLoader;
+
+impl AssetLoader for SketchFileLoader {
+    type Asset = SketchFile;
+    type Settings = ();
+    type Error = SketchFileError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<SketchFile, SketchFileError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let source = std::str::from_utf8(&bytes)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+        parse(source)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sketch"]
+    }
+}
+
+fn parse(source: &str) -> Result<SketchFile, SketchFileError> {
+    let mut calls = Vec::new();
+    for (index, text) in source.lines().enumerate() {
+        let syntax = |message| SketchFileError::Syntax {
+            line: index + 1,
+            message,
+        };
+        let mut args = Args::new(tokenize(text).map_err(syntax)?);
+        let Some(name) = args.next() else {
+            continue;
+        };
+        let Token::Word(name) = name else {
+            return Err(syntax("expected the name of a call".into()));
+        };
+        let call = parse_call(&name, &mut args)
+            .and_then(|call| args.finish().map(|_| call))
+            .map_err(syntax)?;
+        calls.push(call);
+    }
+    Ok(SketchFile { calls })
+}
+
+fn parse_call(name: &str, args: &mut Args) -> Result<Call, String> {
+    Ok(match name {
+        "size" => Call::Size(args.numbers()?),
+        "background" => Call::Background(args.paint()?),
+        "fill" => Call::Fill(args.paint()?),
+        "no_fill" => Call::NoFill,
+        "stroke" => Call::Stroke(args.paint()?),
+        "no_stroke" => Call::NoStroke,
+        "stroke_weight" => Call::StrokeWeight(args.number()?),
+        "stroke_cap" => Call::StrokeCap(args.choice(&[
+            ("round", StrokeCap::Round),
+            ("square", StrokeCap::Square),
+            ("project", StrokeCap::Project),
+        ])?),
+        "stroke_join" => Call::StrokeJoin(args.choice(&[
+            ("miter", StrokeJoin::Miter),
+            ("bevel", StrokeJoin::Bevel),
+            ("round", StrokeJoin::Round),
+        ])?),
+        "miter_limit" => Call::MiterLimit(args.number()?),
+        "layer" => Call::Layer(args.number()? as i32),
+        "blend_mode" => Call::BlendMode(args.choice(&[
+            ("blend", BlendMode::Blend),
+            ("add", BlendMode::Add),
+            ("subtract", BlendMode::Subtract),
+            ("lightest", BlendMode::Lightest),
+            ("darkest", BlendMode::Darkest),
+            ("multiply", BlendMode::Multiply),
+            ("screen", BlendMode::Screen),
+        ])?),
+        "color_mode" => {
+            let mode = args.choice(&[("rgb", ColorMode::Rgb), ("hsb", ColorMode::Hsb)])?;
+            let max = if args.is_empty() {
+                None
+            } else {
+                Some(args.numbers()?)
+            };
+            Call::ColorMode(mode, max)
+        }
+        "push_matrix" => Call::PushMatrix,
+        "pop_matrix" => Call::PopMatrix,
+        "reset_matrix" => Call::ResetMatrix,
+        "translate" => Call::Translate(args.numbers()?),
+        "rotate" => Call::Rotate(args.number()?),
+        // One number scales both axes, as in Processing.
+        "scale" => {
+            let sx = args.number()?;
+            let sy = if args.is_empty() { sx } else { args.number()? };
+            Call::Scale([sx, sy])
+        }
+        "rect_mode" | "ellipse_mode" => {
+            let mode = args.choice(&[
+                ("corner", ShapeMode::Corner),
+                ("corners", ShapeMode::Corners),
+                ("center", ShapeMode::Center),
+                ("radius", ShapeMode::Radius),
+            ])?;
+            match name {
+                "rect_mode" => Call::RectMode(mode),
+                _ => Call::EllipseMode(mode),
+            }
+        }
+        "point" => Call::Point(args.numbers()?),
+        "line" => Call::Line(args.numbers()?),
+        // Like `rect_rounded` with one radius, or `rect_radii` with four.
+        "rect" => {
+            let bounds = args.numbers()?;
+            let radii = if args.is_empty() {
+                [0.0; 4]
+            } else {
+                let r = args.number()?;
+                if args.is_empty() {
+                    [r; 4]
+                } else {
+                    let [tr, br, bl] = args.numbers()?;
+                    [r, tr, br, bl]
+                }
+            };
+            Call::Rect(bounds, radii)
+        }
+        "square" => Call::Square(args.numbers()?),
+        "ellipse" => Call::Ellipse(args.numbers()?),
+        "circle" => Call::Circle(args.numbers()?),
+        "triangle" => Call::Triangle(args.numbers()?),
+        "quad" => Call::Quad(args.numbers()?),
+        "arc" => {
+            let numbers = args.numbers()?;
+            let mode = if args.is_empty() {
+                ArcMode::Open
+            } else {
+                args.choice(&[
+                    ("open", ArcMode::Open),
+                    ("chord", ArcMode::Chord),
+                    ("pie", ArcMode::Pie),
+                ])?
+            };
+            Call::Arc(numbers, mode)
+        }
+        "bezier" => Call::Bezier(args.numbers()?),
+        "curve" => Call::Curve(args.numbers()?),
+        "begin_shape" => Call::BeginShape(if args.is_empty() {
+            ShapeKind::Polygon
+        } else {
+            args.choice(&[
+                ("polygon", ShapeKind::Polygon),
+                ("points", ShapeKind::Points),
+                ("lines", ShapeKind::Lines),
+                ("triangles", ShapeKind::Triangles),
+                ("triangle_strip", ShapeKind::TriangleStrip),
+                ("triangle_fan", ShapeKind::TriangleFan),
+                ("quads", ShapeKind::Quads),
+                ("quad_strip", ShapeKind::QuadStrip),
+            ])?
+        }),
+        "vertex" => Call::Vertex(args.numbers()?),
+        "bezier_vertex" => Call::BezierVertex(args.numbers()?),
+        "quadratic_vertex" => Call::QuadraticVertex(args.numbers()?),
+        "curve_vertex" => Call::CurveVertex(args.numbers()?),
+        "begin_contour" => Call::BeginContour,
+        "end_contour" => Call::EndContour,
+        "end_shape" => Call::EndShape(if args.is_empty() {
+            EndShape::Open
+        } else {
+            args.choice(&[("open", EndShape::Open), ("close", EndShape::Close)])?
+        }),
+        "text_font" => Call::TextFont(args.string()?),
+        "text_size" => Call::TextSize(args.number()?),
+        "text_align" => {
+            let align = args.choice(&[
+                ("left", TextAlign::Left),
+                ("center", TextAlign::Center),
+                ("right", TextAlign::Right),
+            ])?;
+            let baseline = if args.is_empty() {
+                TextBaseline::Baseline
+            } else {
+                args.choice(&[
+                    ("top", TextBaseline::Top),
+                    ("center", TextBaseline::Center),
+                    ("baseline", TextBaseline::Baseline),
+                    ("bottom", TextBaseline::Bottom),
+                ])?
+            };
+            Call::TextAlign(align, baseline)
+        }
+        "text" => Call::Text(args.string()?, args.numbers()?),
+        "image_mode" => Call::ImageMode(args.choice(&[
+            ("corner", ImageMode::Corner),
+            ("corners", ImageMode::Corners),
+            ("center", ImageMode::Center),
+        ])?),
+        "tint" => Call::Tint(args.paint()?),
+        "no_tint" => Call::NoTint,
+        "image" => {
+            let path = args.string()?;
+            let position = args.numbers()?;
+            let extent = if args.is_empty() {
+                None
+            } else {
+                Some(args.numbers()?)
+            };
+            Call::Image(path, position, extent)
+        }
+        _ => return Err(format!("unknown call `{name}`")),
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    /// A `"double quoted"` string, with `\"`, `\\` and `\n` escapes.
+    Quoted(String),
+}
+
+/// Splits a line into tokens, dropping anything after `//`.
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut content = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => content.push('\n'),
+                        Some(escaped @ ('"' | '\\')) => content.push(escaped),
+                        _ => return Err("unknown escape in string".into()),
+                    },
+                    Some(c) => content.push(c),
+                    None => return Err("unterminated string".into()),
+                }
+            }
+            tokens.push(Token::Quoted(content));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            if let Some(comment) = word.find("//") {
+                word.truncate(comment);
+                if !word.is_empty() {
+                    tokens.push(Token::Word(word));
+                }
+                break;
+            }
+            tokens.push(Token::Word(word));
+        }
+    }
+    Ok(tokens)
+}
+
+/// The arguments of one call, consumed from the front.
+struct Args {
+    tokens: std::vec::IntoIter<Token>,
+}
+
+impl Args {
+    fn new(tokens: Vec<Token>) -> Self {
+        Self {
+            tokens: tokens.into_iter(),
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.tokens.next()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tokens.len() == 0
+    }
+
+    fn word(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            Some(Token::Quoted(text)) => {
+                Err(format!("expected a number or name, found \"{text}\""))
+            }
+            None => Err("too few arguments".into()),
+        }
+    }
+
+    fn number(&mut self) -> Result<f32, String> {
+        let word = self.word()?;
+        let value = match word.as_str() {
+            "PI" => std::f32::consts::PI,
+            "HALF_PI" => std::f32::consts::FRAC_PI_2,
+            "QUARTER_PI" => std::f32::consts::FRAC_PI_4,
+            "TWO_PI" | "TAU" => std::f32::consts::TAU,
+            _ => word
+                .parse()
+                .map_err(|_| format!("expected a number, found `{word}`"))?,
+        };
+        Ok(value)
+    }
+
+    fn numbers<const N: usize>(&mut self) -> Result<[f32; N], String> {
+        let mut numbers = [0.0; N];
+        for number in &mut numbers {
+            *number = self.number()?;
+        }
+        Ok(numbers)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Quoted(text)) => Ok(text),
+            Some(Token::Word(word)) => Err(format!("expected a \"quoted\" string, found `{word}`")),
+            None => Err("too few arguments".into()),
+        }
+    }
+
+    fn choice<T: Copy>(&mut self, options: &[(&str, T)]) -> Result<T, String> {
+        let word = self.word()?;
+        options
+            .iter()
+            .find(|(name, _)| *name == word)
+            .map(|(_, value)| *value)
+            .ok_or_else(|| {
+                let names: Vec<_> = options.iter().map(|(name, _)| *name).collect();
+                format!("expected one of {}, found `{word}`", names.join(", "))
+            })
+    }
+
+    /// Takes every remaining argument as a color.
+    fn paint(&mut self) -> Result<Paint, String> {
+        if let Some(Token::Word(word)) = self.tokens.as_slice().first()
+            && word.starts_with('#')
+        {
+            let word = self.word()?;
+            return hex_color(&word)
+                .map(Paint::Hex)
+                .ok_or_else(|| format!("`{word}` is not a #rrggbb color"));
+        }
+        let mut values = Vec::new();
+        while !self.is_empty() {
+            values.push(self.number()?);
+        }
+        match values[..] {
+            [v] => Ok(Paint::Gray(v)),
+            [v, a] => Ok(Paint::GrayAlpha(v, a)),
+            [a, b, c] => Ok(Paint::Color(a, b, c)),
+            [a, b, c, alpha] => Ok(Paint::ColorAlpha(a, b, c, alpha)),
+            _ => Err(format!(
+                "a color takes 1 to 4 numbers or a #rrggbb code, not {}",
+                values.len()
+            )),
+        }
+    }
+
+    fn finish(mut self) -> Result<(), String> {
+        match self.next() {
+            None => Ok(()),
+            Some(_) => Err("too many arguments".into()),
+        }
+    }
+}
+
+/// Replays each canvas's sketch file. Until the file has loaded, and while a
+/// reload fails, the canvas keeps drawing the last version that parsed.
+pub(super) fn draw_sketch_files(
+    mut sketch: SketchContext,
+    sources: Query<(Entity, &SketchSource)>,
+    files: Res<Assets<SketchFile>>,
+) {
+    for (canvas, source) in &sources {
+        if let Some(file) = files.get(&source.0) {
+            sketch.draw(canvas, || file.replay());
+        }
+    }
+}
+
+/// Logs each reload, so it's clear the saved file was picked up.
+pub(super) fn report_reloads(
+    mut events: EventReader<AssetEvent<SketchFile>>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Modified { id } = event
+            && let Some(path) = asset_server.get_path(*id)
+        {
+            info!("reloaded {path}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calls(source: &str) -> Vec<Call> {
+        match parse(source) {
+            Ok(file) => file.calls,
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    /// The line and message parsing `source` fails with.
+    fn error(source: &str) -> (usize, String) {
+        match parse(source) {
+            Ok(file) => panic!("parsed as {:?}", file.calls),
+            Err(SketchFileError::Syntax { line, message }) => (line, message),
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let source = "// A header.\n\n   \nfill 255 // white\nrect 1 2 3 4// no space\n//no_fill";
+        let calls = calls(source);
+        assert!(matches!(
+            calls[..],
+            [
+                Call::Fill(Paint::Gray(255.0)),
+                Call::Rect([1.0, 2.0, 3.0, 4.0], [0.0, 0.0, 0.0, 0.0])
+            ]
+        ));
+    }
+
+    #[test]
+    fn strings_keep_slashes_and_escapes() {
+        let calls = calls(r#"text "http://a \"b\"\n" 10 20"#);
+        let [Call::Text(content, [10.0, 20.0])] = &calls[..] else {
+            panic!("{calls:?}");
+        };
+        assert_eq!(content, "http://a \"b\"\n");
+        assert_eq!(
+            error(r#"text "open 1 2"#),
+            (1, "unterminated string".into())
+        );
+        assert_eq!(
+            error(r#"text "\t" 1 2"#),
+            (1, "unknown escape in string".into())
+        );
+    }
+
+    #[test]
+    fn arguments() {
+        let calls = calls(
+            "rotate HALF_PI\nscale 2\nrect 0 0 10 10 1 2 3 4\narc 0 0 5 5 0 PI pie\nfill #ff0000\nstroke 1 2 3 4",
+        );
+        assert!(matches!(
+            calls[..],
+            [
+                Call::Rotate(std::f32::consts::FRAC_PI_2),
+                Call::Scale([2.0, 2.0]),
+                Call::Rect(_, [1.0, 2.0, 3.0, 4.0]),
+                Call::Arc([.., std::f32::consts::PI], ArcMode::Pie),
+                Call::Fill(Paint::Hex(_)),
+                Call::Stroke(Paint::ColorAlpha(1.0, 2.0, 3.0, 4.0)),
+            ]
+        ));
+    }
+
+    #[test]
+    fn bad_argument_counts() {
+        assert_eq!(error("line 1 2 3"), (1, "too few arguments".into()));
+        assert_eq!(error("point 1 2 3"), (1, "too many arguments".into()));
+        assert_eq!(error("no_fill 0"), (1, "too many arguments".into()));
+        // One radius or four, not two.
+        assert_eq!(error("rect 0 0 10 10 1 2"), (1, "too few arguments".into()));
+        assert_eq!(
+            error("fill 1 2 3 4 5"),
+            (
+                1,
+                "a color takes 1 to 4 numbers or a #rrggbb code, not 5".into()
+            )
+        );
+    }
+
+    #[test]
+    fn bad_arguments() {
+        assert_eq!(
+            error("circle 1 two 3"),
+            (1, "expected a number, found `two`".into())
+        );
+        assert_eq!(
+            error("stroke_cap butt"),
+            (
+                1,
+                "expected one of round, square, project, found `butt`".into()
+            )
+        );
+        assert_eq!(
+            error("text hello 1 2"),
+            (1, "expected a \"quoted\" string, found `hello`".into())
+        );
+        assert_eq!(
+            error("fill #12345"),
+            (1, "`#12345` is not a #rrggbb color".into())
+        );
+    }
+
+    #[test]
+    fn unknown_calls() {
+        assert_eq!(error("circel 1 2 3"), (1, "unknown call `circel`".into()));
+        assert_eq!(
+            error(r#""circle" 1 2 3"#),
+            (1, "expected the name of a call".into())
+        );
+    }
+
+    #[test]
+    fn errors_count_lines_from_one() {
+        let source = "// comment\n\nbackground 0\n  fill 255 // white\nellipse 1 2 3\nrect 1 2 3 4";
+        assert_eq!(error(source), (5, "too few arguments".into()));
+        assert_eq!(
+            parse(source).map(|_| ()).unwrap_err().to_string(),
+            "line 5: too few arguments"
+        );
+    }
+}
@@ -0,0 +1,730 @@
+//! A CPU renderer for `save()`/`save_frame()` and `load_pixels()`, and the
+//! headless mode that runs a sketch without a window or GPU, e.g. to compare
+//! frames against golden images.
+//!
+//! It rasterizes the triangles of the shape meshes, images and text in the
+//! same depth order and blend modes the GPU draws, so a saved frame matches the
+//! window up to anti-aliasing and text shaping. Every pixel averages a grid of
+//! coverage samples.
+
+use std::time::Duration;
+
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::image::{ImageFilterMode, ImageSampler};
+use bevy::prelude::*;
+use bevy::render::RenderPlugin;
+use bevy::render::mesh::{PrimitiveTopology, VertexAttributeValues};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::settings::WgpuSettings;
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::ExitCondition;
+use bevy::winit::WinitPlugin;
+
+use super::blend::BlendMode;
+use super::graphics::{GraphicsLayer, layer_image};
+use super::image::{ImageQueue, QueuedImage, quad};
+use super::text::{QueuedText, TextQueue, glyph_contours};
+use super::{
+    ProcessingCommand, SketchMesh, SketchState, WindowCanvas, frame_count, rasterize_frame,
+    tessellate, with_state, world_to_canvas,
+};
+
+/// Coverage samples per pixel along each axis.
+const SUBSAMPLES: usize = 4;
+
+/// Saves the frame being drawn as an image, once `draw()` has finished it. The
+/// format follows the extension, e.g. `save("out.png")`.
+pub fn save(path: &str) {
+    with_state(|s| s.saves.push(path.to_owned()));
+}
+/// Like `save()`, with the last run of `#` in `pattern` replaced by the frame
+/// number: `save_frame("frame-####.png")` writes `frame-0001.png`, `frame-0002.png`, ...
+pub fn save_frame(pattern: &str) {
+    save(&numbered(pattern, frame_count()));
+}
+
+fn numbered(pattern: &str, frame: u32) -> String {
+    let Some(end) = pattern.rfind('#').map(|i| i + 1) else {
+        return pattern.to_owned();
+    };
+    let start = pattern[..end].trim_end_matches('#').len();
+    format!(
+        "{}{:0width$}{}",
+        &pattern[..start],
+        frame,
+        &pattern[end..],
+        width = end - start
+    )
+}
+
+/// An RGBA buffer of linear colors, `SUBSAMPLES`² samples per pixel.
+struct Canvas {
+    width: usize,
+    height: usize,
+    samples: Vec<LinearRgba>,
+    /// How everything drawn next combines with the samples.
+    mode: BlendMode,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize, background: Color) -> Self {
+        Self {
+            width,
+            height,
+            samples: vec![background.to_linear(); width * height * SUBSAMPLES * SUBSAMPLES],
+            mode: BlendMode::Blend,
+        }
+    }
+
+    fn sample_width(&self) -> usize {
+        self.width * SUBSAMPLES
+    }
+
+    fn sample_height(&self) -> usize {
+        self.height * SUBSAMPLES
+    }
+
+    /// Composites `color` onto the sample at `index` in the current blend mode.
+    fn blend(&mut self, index: usize, color: LinearRgba) {
+        let dst = &mut self.samples[index];
+        *dst = self.mode.apply(color, *dst);
+    }
+
+    /// Samples whose centres fall in `lo..hi` along an axis of `len` samples.
+    fn span(lo: f32, hi: f32, len: usize) -> std::ops::Range<usize> {
+        let first = (lo - 0.5).ceil().max(0.0) as usize;
+        let end = ((hi - 0.5).ceil().max(0.0) as usize).min(len);
+        first..end.max(first)
+    }
+
+    /// Fills the triangle `v` (in pixels), colouring each covered sample with
+    /// `shade`, which receives the sample's barycentric weights for `v`.
+    fn fill_triangle(&mut self, v: [Vec2; 3], mut shade: impl FnMut([f32; 3]) -> LinearRgba) {
+        let p = v.map(|p| p * SUBSAMPLES as f32);
+        let area = (p[1] - p[0]).perp_dot(p[2] - p[0]);
+        if area.abs() <= f32::EPSILON {
+            return;
+        }
+        // Walk every triangle the same way round, so that an edge shared by two
+        // triangles is owned by exactly one of them and never blended twice.
+        let order = if area > 0.0 { [0, 1, 2] } else { [0, 2, 1] };
+        let area = area.abs();
+        let q = order.map(|i| p[i]);
+        let edges = [(q[1], q[2]), (q[2], q[0]), (q[0], q[1])];
+        let owned = edges.map(|(from, to)| {
+            let d = to - from;
+            d.y > 0.0 || (d.y == 0.0 && d.x > 0.0)
+        });
+
+        let min = p[0].min(p[1]).min(p[2]);
+        let max = p[0].max(p[1]).max(p[2]);
+        let sw = self.sample_width();
+        let rows = Self::span(min.y, max.y + 1.0, self.sample_height());
+        let columns = Self::span(min.x, max.x + 1.0, sw);
+        for sy in rows {
+            'sample: for sx in columns.clone() {
+                let center = Vec2::new(sx as f32 + 0.5, sy as f32 + 0.5);
+                let mut weights = [0.0; 3];
+                for (k, &(from, to)) in edges.iter().enumerate() {
+                    let e = (to - from).perp_dot(center - from);
+                    if e < 0.0 || (e == 0.0 && !owned[k]) {
+                        continue 'sample;
+                    }
+                    // Edge k lies opposite vertex `order[k]`.
+                    weights[order[k]] = e / area;
+                }
+                let color = shade(weights);
+                self.blend(sy * sw + sx, color);
+            }
+        }
+    }
+
+    /// Fills closed `contours` (in pixels) with the non-zero winding rule.
+    fn fill_path(&mut self, contours: &[Vec<Vec2>], color: LinearRgba) {
+        let s = SUBSAMPLES as f32;
+        let edges: Vec<(Vec2, Vec2)> = contours
+            .iter()
+            .filter(|c| c.len() >= 3)
+            .flat_map(|c| (0..c.len()).map(move |i| (c[i] * s, c[(i + 1) % c.len()] * s)))
+            .filter(|(a, b)| a.y != b.y)
+            .collect();
+        let Some((top, bottom)) = edges
+            .iter()
+            .map(|(a, b)| (a.y.min(b.y), a.y.max(b.y)))
+            .reduce(|x, y| (x.0.min(y.0), x.1.max(y.1)))
+        else {
+            return;
+        };
+        let sw = self.sample_width();
+        let mut crossings = Vec::new();
+        for sy in Self::span(top, bottom, self.sample_height()) {
+            let y = sy as f32 + 0.5;
+            crossings.clear();
+            for &(a, b) in &edges {
+                // Half-open in y, so a vertex shared by two edges counts once.
+                if (a.y <= y) != (b.y <= y) {
+                    let x = a.x + (y - a.y) * (b.x - a.x) / (b.y - a.y);
+                    crossings.push((x, if b.y > a.y { 1 } else { -1 }));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                if winding != 0 {
+                    for sx in Self::span(pair[0].0, pair[1].0, sw) {
+                        self.blend(sy * sw + sx, color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The average of a pixel's samples.
+    fn pixel(&self, x: usize, y: usize) -> LinearRgba {
+        let sw = self.sample_width();
+        let mut sum = LinearRgba::NONE;
+        for j in 0..SUBSAMPLES {
+            let row = (y * SUBSAMPLES + j) * sw + x * SUBSAMPLES;
+            for sample in &self.samples[row..row + SUBSAMPLES] {
+                sum += *sample;
+            }
+        }
+        sum * (1.0 / (SUBSAMPLES * SUBSAMPLES) as f32)
+    }
+
+    /// Averages each pixel's samples into 8-bit sRGB.
+    fn resolve(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.width * self.height * 4);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pixel = Color::from(self.pixel(x, y)).to_srgba().to_u8_array();
+                data.extend_from_slice(&pixel);
+            }
+        }
+        data
+    }
+
+    /// Each pixel's color, row by row from the top-left corner. Drawn on a
+    /// transparent background, the samples hold premultiplied colors, which
+    /// this divides out again.
+    fn colors(&self) -> Vec<Color> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let p = self.pixel(x, y);
+                if p.alpha > 0.0 {
+                    LinearRgba::new(
+                        p.red / p.alpha,
+                        p.green / p.alpha,
+                        p.blue / p.alpha,
+                        p.alpha,
+                    )
+                    .into()
+                } else {
+                    Color::NONE
+                }
+            })
+            .collect()
+    }
+
+    /// Composites the canvas over `image`, as a `LayerCamera` does. Drawn on a
+    /// transparent background, the samples hold premultiplied colors.
+    fn composite_onto(&self, image: &mut Image) {
+        for y in 0..self.height as u32 {
+            for x in 0..self.width as u32 {
+                let src = self.pixel(x as usize, y as usize);
+                let dst = image
+                    .get_color_at(x, y)
+                    .map(|c| c.to_linear())
+                    .unwrap_or(LinearRgba::NONE);
+                let _ = image.set_color_at(x, y, (src + dst * (1.0 - src.alpha)).into());
+            }
+        }
+    }
+}
+
+/// Lookup at `uv` with clamped edges: bilinear like the default image
+/// sampler, or the nearest texel for an image whose sampler asks for that.
+fn sample_image(image: &Image, uv: Vec2) -> LinearRgba {
+    let size = image.size();
+    let nearest = matches!(&image.sampler, ImageSampler::Descriptor(descriptor)
+        if matches!(descriptor.mag_filter, ImageFilterMode::Nearest));
+    if nearest {
+        let texel = (uv * size.as_vec2()).floor().as_uvec2().min(size - 1);
+        return image
+            .get_color_at(texel.x, texel.y)
+            .map(|c| c.to_linear())
+            .unwrap_or(LinearRgba::NONE);
+    }
+    let texel = uv * size.as_vec2() - 0.5;
+    let base = texel.floor();
+    let t = texel - base;
+    let fetch = |dx: i32, dy: i32| {
+        let x = (base.x as i32 + dx).clamp(0, size.x as i32 - 1) as u32;
+        let y = (base.y as i32 + dy).clamp(0, size.y as i32 - 1) as u32;
+        image
+            .get_color_at(x, y)
+            .map(|c| c.to_linear())
+            .unwrap_or(LinearRgba::NONE)
+    };
+    let top = fetch(0, 0) * (1.0 - t.x) + fetch(1, 0) * t.x;
+    let bottom = fetch(0, 1) * (1.0 - t.x) + fetch(1, 1) * t.x;
+    top * (1.0 - t.y) + bottom * t.y
+}
+
+/// Mixes three vertex values by barycentric `weights`.
+fn interpolate<T>(values: [T; 3], weights: [f32; 3]) -> T
+where
+    T: std::ops::Mul<f32, Output = T> + std::ops::Add<Output = T> + Copy,
+{
+    values[0] * weights[0] + values[1] * weights[1] + values[2] * weights[2]
+}
+
+/// Where `render` finds the images and fonts a frame draws with.
+pub(super) trait FrameAssets {
+    fn image(&self, id: AssetId<Image>) -> Option<&Image>;
+    /// The bytes of a font file.
+    fn font(&self, id: AssetId<Font>) -> Option<&[u8]>;
+}
+
+impl FrameAssets for (&Assets<Image>, &Assets<Font>) {
+    fn image(&self, id: AssetId<Image>) -> Option<&Image> {
+        self.0.get(id)
+    }
+    fn font(&self, id: AssetId<Font>) -> Option<&[u8]> {
+        self.1.get(id).map(|font| &font.data[..])
+    }
+}
+
+/// One thing `render` draws, at the depth the GPU sorts it by.
+enum Layer<'a> {
+    Shapes(BlendMode, &'a Mesh),
+    Image(&'a QueuedImage),
+    Text(&'a QueuedText),
+}
+
+/// Draws this frame's triangles, images and text into a `Canvas`, back to front.
+fn render(
+    size: Vec2,
+    background: Color,
+    batches: &[(BlendMode, &Mesh, f32)],
+    image_queue: &ImageQueue,
+    text_queue: &TextQueue,
+    assets: &impl FrameAssets,
+) -> Canvas {
+    let mut canvas = Canvas::new(size.x as usize, size.y as usize, background);
+
+    let mut layers: Vec<(f32, Layer)> = batches
+        .iter()
+        .map(|&(mode, mesh, z)| (z, Layer::Shapes(mode, mesh)))
+        .chain(image_queue.0.iter().map(|i| (i.z, Layer::Image(i))))
+        .chain(text_queue.0.iter().map(|t| (t.z, Layer::Text(t))))
+        .collect();
+    layers.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for (_, layer) in layers {
+        match layer {
+            Layer::Shapes(mode, mesh) => draw_mesh(&mut canvas, mode, mesh, size),
+            Layer::Image(queued) => draw_image(&mut canvas, queued, assets, size),
+            Layer::Text(text) => {
+                let font = assets.font(text.text_style.font.id());
+                canvas.fill_path(&glyph_contours(text, font), text.color.to_linear())
+            }
+        }
+    }
+    canvas
+}
+
+/// Fills a shape batch's triangles, blending them in `mode`.
+fn draw_mesh(canvas: &mut Canvas, mode: BlendMode, mesh: &Mesh, size: Vec2) {
+    let (
+        Some(VertexAttributeValues::Float32x3(positions)),
+        Some(VertexAttributeValues::Float32x4(colors)),
+        Some(indices),
+    ) = (
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+        mesh.attribute(Mesh::ATTRIBUTE_COLOR),
+        mesh.indices(),
+    )
+    else {
+        return;
+    };
+    canvas.mode = mode;
+    let indices: Vec<usize> = indices.iter().collect();
+    for tri in indices.chunks_exact(3) {
+        let v = [tri[0], tri[1], tri[2]]
+            .map(|i| world_to_canvas(Vec2::new(positions[i][0], positions[i][1]), size));
+        let c = [tri[0], tri[1], tri[2]].map(|i| LinearRgba::from_f32_array(colors[i]));
+        canvas.fill_triangle(v, |w| interpolate(c, w));
+    }
+    canvas.mode = BlendMode::Blend;
+}
+
+fn draw_image(canvas: &mut Canvas, queued: &QueuedImage, assets: &impl FrameAssets, size: Vec2) {
+    let Some(image) = assets.image(queued.image.id()) else {
+        return;
+    };
+    let (corners, uv) = quad(queued, image.size_f32(), size);
+    let corners = corners.map(|p| world_to_canvas(p, size));
+    let uvs = [
+        uv.min,
+        Vec2::new(uv.max.x, uv.min.y),
+        uv.max,
+        Vec2::new(uv.min.x, uv.max.y),
+    ];
+    let tint = queued.tint.to_linear();
+    for [a, b, c] in [[0, 1, 2], [0, 2, 3]] {
+        canvas.fill_triangle([corners[a], corners[b], corners[c]], |w| {
+            let texel = sample_image(image, interpolate([uvs[a], uvs[b], uvs[c]], w));
+            LinearRgba::from_vec4(texel.to_vec4() * tint.to_vec4())
+        });
+    }
+}
+
+/// The pixels `commands` draw on a transparent `size` canvas, row by row from
+/// the top-left corner, for `load_pixels()`.
+pub(super) fn render_pixels(
+    commands: Vec<ProcessingCommand>,
+    size: Vec2,
+    assets: &impl FrameAssets,
+) -> Vec<Color> {
+    let (mut text_queue, mut image_queue) = (TextQueue::default(), ImageQueue::default());
+    let batches: Vec<_> = tessellate(commands, size, &mut text_queue, &mut image_queue)
+        .into_iter()
+        .map(|builder| {
+            let (mode, z) = (builder.mode, builder.z);
+            let mut mesh = Mesh::new(
+                PrimitiveTopology::TriangleList,
+                RenderAssetUsages::MAIN_WORLD,
+            );
+            builder.write_to(&mut mesh);
+            (mode, mesh, z)
+        })
+        .collect();
+    let batches: Vec<_> = batches
+        .iter()
+        .map(|(mode, mesh, z)| (*mode, mesh, *z))
+        .collect();
+    render(
+        size,
+        Color::NONE,
+        &batches,
+        &image_queue,
+        &text_queue,
+        assets,
+    )
+    .colors()
+}
+
+/// Writes each canvas's frame to every path passed to `save()` on it during
+/// this frame. Runs after `rasterize_frame`, before the text and image queues are
+/// consumed. Areas the sketch leaves uncovered show the clear color.
+pub(super) fn save_frames(
+    mut canvases: Query<(&mut SketchState, &SketchMesh, &ImageQueue, &TextQueue)>,
+    clear_color: Res<ClearColor>,
+    meshes: Res<Assets<Mesh>>,
+    images: Res<Assets<Image>>,
+    fonts: Res<Assets<Font>>,
+) {
+    for (mut state, sketch_mesh, image_queue, text_queue) in &mut canvases {
+        if state.saves.is_empty() {
+            continue;
+        }
+        let canvas = render(
+            state.surface.size,
+            clear_color.0,
+            &frame_batches(sketch_mesh, &meshes),
+            image_queue,
+            text_queue,
+            &(&*images, &*fonts),
+        );
+        write_png(&canvas, state.saves.drain(..));
+    }
+}
+
+/// This frame's shape meshes with their blend modes and depths.
+/// `rasterize_frame` leaves stale vertices in the batches it has hidden.
+fn frame_batches<'a>(
+    sketch_mesh: &SketchMesh,
+    meshes: &'a Assets<Mesh>,
+) -> Vec<(BlendMode, &'a Mesh, f32)> {
+    sketch_mesh.batches[..sketch_mesh.used]
+        .iter()
+        .filter_map(|batch| Some((batch.mode, meshes.get(&batch.mesh)?, batch.z)))
+        .collect()
+}
+
+/// Draws each `create_graphics()` layer's frame into its image in place of the
+/// `LayerCamera`s, which have no GPU to run on in headless mode.
+fn render_graphics(
+    layers: Query<(
+        &GraphicsLayer,
+        &SketchState,
+        &SketchMesh,
+        &ImageQueue,
+        &TextQueue,
+    )>,
+    meshes: Res<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+    fonts: Res<Assets<Font>>,
+) {
+    for (layer, state, sketch_mesh, image_queue, text_queue) in &layers {
+        if sketch_mesh.used == 0 && image_queue.0.is_empty() && text_queue.0.is_empty() {
+            continue;
+        }
+        let canvas = render(
+            state.surface.size,
+            Color::NONE,
+            &frame_batches(sketch_mesh, &meshes),
+            image_queue,
+            text_queue,
+            &(&*images, &*fonts),
+        );
+        if let Some(image) = images.get_mut(layer_image(layer)) {
+            canvas.composite_onto(image);
+        }
+    }
+}
+
+fn write_png(canvas: &Canvas, paths: impl Iterator<Item = String>) {
+    let image = Image::new(
+        Extent3d {
+            width: canvas.width as u32,
+            height: canvas.height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        canvas.resolve(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::MAIN_WORLD,
+    );
+    let Ok(pixels) = image.try_into_dynamic() else {
+        return;
+    };
+    for path in paths {
+        if let Err(err) = pixels.save(&path) {
+            warn!("could not save frame to {path}: {err}");
+        }
+    }
+}
+
+/// Reads `--headless [frames] [output]` from the command line.
+pub(super) fn headless_from_args() -> Option<Headless> {
+    parse_headless(std::env::args())
+}
+
+fn parse_headless(args: impl IntoIterator<Item = String>) -> Option<Headless> {
+    let args: Vec<String> = args.into_iter().collect();
+    let at = args.iter().position(|arg| arg == "--headless")?;
+    let mut rest = args[at + 1..].iter().peekable();
+    let frames = match rest.peek().and_then(|n| n.parse().ok()) {
+        Some(n) => {
+            rest.next();
+            n
+        }
+        None => 1,
+    };
+    Some(Headless {
+        frames,
+        output: rest.next().cloned(),
+    })
+}
+
+/// Runs the sketch without a window or GPU for `frames` frames, saving the last
+/// frame of the `WindowCanvas` to `output` if given. The clock advances a fixed
+/// 1/60 s per frame, so `millis()`, and with it every frame, is the same from
+/// run to run.
+pub(super) struct Headless {
+    pub frames: u32,
+    pub output: Option<String>,
+}
+
+impl Plugin for Headless {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    close_when_requested: false,
+                })
+                .set(RenderPlugin {
+                    render_creation: WgpuSettings {
+                        backends: None,
+                        ..default()
+                    }
+                    .into(),
+                    ..default()
+                })
+                .disable::<WinitPlugin>(),
+        )
+        .add_plugins(ScheduleRunnerPlugin::run_loop(Duration::ZERO))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1.0 / 60.0,
+        )))
+        .add_systems(
+            Update,
+            render_graphics.after(rasterize_frame).before(save_frames),
+        );
+        let frames = self.frames.max(1);
+        let output = self.output.clone();
+        // Counted in app frames rather than `frame_count()`, which stands still
+        // while a sketch isn't drawing.
+        app.add_systems(
+            First,
+            move |mut canvases: Query<&mut SketchState, With<WindowCanvas>>,
+                  mut frame: Local<u32>| {
+                *frame += 1;
+                if *frame == frames
+                    && let Some(path) = &output
+                {
+                    for mut state in &mut canvases {
+                        state.saves.push(path.clone());
+                    }
+                }
+            },
+        )
+        .add_systems(
+            Last,
+            move |mut exit: EventWriter<AppExit>, mut frame: Local<u32>| {
+                *frame += 1;
+                if *frame >= frames {
+                    exit.write(AppExit::Success);
+                }
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Affine2;
+
+    use super::super::{ProcessingCommand, Style, tessellate};
+    use super::*;
+
+    /// Rasterizes `commands` onto a black `size` canvas the way headless
+    /// frames are, through the shape meshes.
+    fn render(size: Vec2, commands: Vec<ProcessingCommand>) -> Canvas {
+        let mut canvas = Canvas::new(size.x as usize, size.y as usize, Color::BLACK);
+        let builders = tessellate(
+            commands,
+            size,
+            &mut TextQueue::default(),
+            &mut ImageQueue::default(),
+        );
+        for builder in builders {
+            let mode = builder.mode;
+            let mut mesh = Mesh::new(
+                bevy::render::mesh::PrimitiveTopology::TriangleList,
+                RenderAssetUsages::default(),
+            );
+            builder.write_to(&mut mesh);
+            draw_mesh(&mut canvas, mode, &mesh, size);
+        }
+        canvas
+    }
+
+    fn fill_only() -> Style {
+        Style {
+            fill: Some(Color::WHITE),
+            stroke: None,
+            ..default()
+        }
+    }
+
+    /// Compares each pixel's red channel, row by row, with `expected`.
+    fn assert_coverage(canvas: &Canvas, expected: [[f32; 4]; 4]) {
+        for (y, row) in expected.iter().enumerate() {
+            for (x, &coverage) in row.iter().enumerate() {
+                let red = canvas.pixel(x, y).red;
+                assert!(
+                    (red - coverage).abs() < 1e-4,
+                    "pixel ({x}, {y}) is {red}, expected {coverage}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rect_covers_whole_and_half_pixels() {
+        let canvas = render(
+            Vec2::splat(4.0),
+            vec![ProcessingCommand::Rect {
+                x: 0.5,
+                y: 1.0,
+                w: 2.5,
+                h: 2.0,
+                radii: [0.0; 4],
+                style: fill_only(),
+                transform: Affine2::IDENTITY,
+            }],
+        );
+        assert_coverage(
+            &canvas,
+            [
+                [0.0, 0.0, 0.0, 0.0],
+                [0.5, 1.0, 1.0, 0.0],
+                [0.5, 1.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 0.0],
+            ],
+        );
+    }
+
+    #[test]
+    fn triangle_counts_the_samples_inside_its_edge() {
+        // The long edge is x + 2y = 4, which passes through no sample centre,
+        // so each pixel's coverage is an exact count of its 16 samples.
+        let canvas = render(
+            Vec2::splat(4.0),
+            vec![ProcessingCommand::Triangle {
+                x1: 0.0,
+                y1: 0.0,
+                x2: 4.0,
+                y2: 0.0,
+                x3: 0.0,
+                y3: 2.0,
+                style: fill_only(),
+                transform: Affine2::IDENTITY,
+            }],
+        );
+        assert_coverage(
+            &canvas,
+            [
+                [1.0, 1.0, 0.75, 0.25],
+                [0.75, 0.25, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 0.0],
+            ],
+        );
+    }
+
+    #[test]
+    fn numbered_fills_the_last_run_of_hashes() {
+        assert_eq!(numbered("frame-####.png", 7), "frame-0007.png");
+        assert_eq!(numbered("take#2-##.png", 3), "take#2-03.png");
+        assert_eq!(numbered("frame-##.png", 12345), "frame-12345.png");
+        assert_eq!(numbered("frame.png", 7), "frame.png");
+    }
+
+    fn parse(args: &[&str]) -> Option<(u32, Option<String>)> {
+        let args = ["sketch"].iter().chain(args).map(|arg| arg.to_string());
+        parse_headless(args).map(|headless| (headless.frames, headless.output))
+    }
+
+    #[test]
+    fn parses_headless_arguments() {
+        assert_eq!(parse(&[]), None);
+        assert_eq!(parse(&["--headless"]), Some((1, None)));
+        assert_eq!(parse(&["--headless", "60"]), Some((60, None)));
+        assert_eq!(
+            parse(&["--headless", "60", "out.png"]),
+            Some((60, Some("out.png".into())))
+        );
+        assert_eq!(
+            parse(&["--headless", "out.png"]),
+            Some((1, Some("out.png".into())))
+        );
+    }
+}
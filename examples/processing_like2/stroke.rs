@@ -0,0 +1,276 @@
+//! Stroke tessellation: a polyline widened into triangles, with `stroke_cap()`
+//! ends and `stroke_join()` corners. No two triangles overlap, so translucent
+//! strokes come out evenly colored, corners included.
+
+use bevy::math::Vec2;
+
+use super::curves::elliptical_arc;
+use super::with_state;
+
+/// How the ends of open lines are drawn.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum StrokeCap {
+    /// Rounded off by a half circle.
+    #[default]
+    Round,
+    /// Cut off square at the end point.
+    Square,
+    /// Cut off square, half the stroke weight past the end point.
+    Project,
+}
+
+/// How the corners between the segments of a line are drawn.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum StrokeJoin {
+    /// Extended to a point, or beveled past the miter limit.
+    #[default]
+    Miter,
+    /// Cut off flat.
+    Bevel,
+    /// Rounded off by an arc.
+    Round,
+}
+
+/// The cap, join and miter limit a stroke is drawn with.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(super) struct Pen {
+    pub(super) cap: StrokeCap,
+    pub(super) join: StrokeJoin,
+    /// Longest a miter may be, in stroke weights, before it is beveled instead.
+    pub(super) miter_limit: f32,
+}
+
+impl Default for Pen {
+    fn default() -> Self {
+        Self {
+            cap: StrokeCap::Round,
+            join: StrokeJoin::Miter,
+            miter_limit: 4.0,
+        }
+    }
+}
+
+/// Sets how the ends of lines and open shapes are drawn; `StrokeCap::Round` by default.
+pub fn stroke_cap(cap: StrokeCap) {
+    with_state(|s| s.style.pen.cap = cap);
+}
+
+/// Sets how the corners of lines and shapes are drawn; `StrokeJoin::Miter` by default.
+pub fn stroke_join(join: StrokeJoin) {
+    with_state(|s| s.style.pen.join = join);
+}
+
+/// Sets how long, in stroke weights, a mitered corner may reach before it is
+/// beveled instead; 4 by default. Sharper corners have longer miters.
+pub fn miter_limit(limit: f32) {
+    with_state(|s| s.style.pen.miter_limit = limit.max(1.0));
+}
+
+/// The corners of one segment's band where it meets a joint: on its left and
+/// right, looking along the line.
+#[derive(Clone, Copy)]
+struct Ends {
+    left: u32,
+    right: u32,
+}
+
+/// The vertices a joint gives the segments before and after it.
+#[derive(Clone, Copy)]
+struct Joint {
+    incoming: Ends,
+    outgoing: Ends,
+}
+
+/// Triangles covering `points` widened to `weight`: vertices and indices into them.
+pub(super) fn tessellate_stroke(
+    points: &[Vec2],
+    closed: bool,
+    weight: f32,
+    pen: &Pen,
+    tolerance: f32,
+) -> (Vec<Vec2>, Vec<u32>) {
+    let mut out = Outline {
+        half: weight * 0.5,
+        tolerance,
+        vertices: Vec::new(),
+        indices: Vec::new(),
+    };
+    let mut points = points.to_vec();
+    points.dedup_by(|a, b| a.distance_squared(*b) < 1e-10);
+    if closed && points.len() > 2 && points[0].distance_squared(points[points.len() - 1]) < 1e-10 {
+        points.pop();
+    }
+
+    let n = points.len();
+    if n < 2 {
+        // A line from a point to itself is just its caps, like Processing's.
+        if let (Some(&p), false) = (points.first(), closed) {
+            out.cap(p, Vec2::X, pen.cap, false);
+            out.cap(p, Vec2::X, pen.cap, true);
+        }
+        return (out.vertices, out.indices);
+    }
+
+    let direction = |i: usize| (points[(i + 1) % n] - points[i]).normalize();
+    let joints: Vec<Joint> = (0..n)
+        .map(|i| {
+            let p = points[i];
+            match (i, closed) {
+                (0, false) => out.end(p, direction(0), pen.cap, false),
+                (i, false) if i == n - 1 => out.end(p, direction(n - 2), pen.cap, true),
+                _ => out.joint(p, direction((i + n - 1) % n), direction(i), pen),
+            }
+        })
+        .collect();
+
+    let segments = if closed { n } else { n - 1 };
+    for i in 0..segments {
+        let from = joints[i].outgoing;
+        let to = joints[(i + 1) % n].incoming;
+        out.indices.extend([
+            from.left, from.right, to.left, to.left, from.right, to.right,
+        ]);
+    }
+    (out.vertices, out.indices)
+}
+
+struct Outline {
+    half: f32,
+    tolerance: f32,
+    vertices: Vec<Vec2>,
+    indices: Vec<u32>,
+}
+
+impl Outline {
+    fn vertex(&mut self, p: Vec2) -> u32 {
+        self.vertices.push(p);
+        self.vertices.len() as u32 - 1
+    }
+
+    /// Triangles from `pivot` to each step along the arc of radius `half`
+    /// around `center`, from `from` to `from + sweep` radians.
+    fn fan(&mut self, pivot: u32, center: Vec2, from: f32, sweep: f32) {
+        let arc = elliptical_arc(
+            center,
+            Vec2::splat(self.half),
+            from,
+            from + sweep,
+            self.tolerance,
+        );
+        let arc: Vec<u32> = arc.into_iter().map(|p| self.vertex(p)).collect();
+        for pair in arc.windows(2) {
+            self.indices.extend([pivot, pair[0], pair[1]]);
+        }
+    }
+
+    /// The cap at an end point `p` of a line heading along `d`: at its start,
+    /// or at its `end`.
+    fn cap(&mut self, p: Vec2, d: Vec2, cap: StrokeCap, end: bool) {
+        let back = if end { d } else { -d };
+        let normal = d.perp() * self.half;
+        match cap {
+            StrokeCap::Square => {}
+            StrokeCap::Project => {
+                let corners =
+                    [p + normal, p - normal, p - normal + back * self.half].map(|c| self.vertex(c));
+                let tip = self.vertex(p + normal + back * self.half);
+                self.indices.extend(corners);
+                self.indices.extend([corners[0], corners[2], tip]);
+            }
+            StrokeCap::Round => {
+                let center = self.vertex(p);
+                // Half a turn from one side of the line to the other, around the back.
+                let from = if end { -normal } else { normal };
+                self.fan(center, p, from.to_angle(), std::f32::consts::PI);
+            }
+        }
+    }
+
+    fn end(&mut self, p: Vec2, d: Vec2, cap: StrokeCap, end: bool) -> Joint {
+        self.cap(p, d, cap, end);
+        let normal = d.perp() * self.half;
+        let ends = Ends {
+            left: self.vertex(p + normal),
+            right: self.vertex(p - normal),
+        };
+        Joint {
+            incoming: ends,
+            outgoing: ends,
+        }
+    }
+
+    /// The corner at `p` between a segment heading along `d_in` and the next
+    /// one heading along `d_out`.
+    fn joint(&mut self, p: Vec2, d_in: Vec2, d_out: Vec2, pen: &Pen) -> Joint {
+        let (a, b) = (d_in.perp(), d_out.perp());
+        let bisector = (a + b).normalize_or_zero();
+        // Half the miter's length, in half stroke weights.
+        let ratio = 1.0 / bisector.dot(a).max(1e-6);
+        let turn = d_in.perp_dot(d_out);
+
+        if turn.abs() < 1e-4 && d_in.dot(d_out) > 0.0 {
+            // Straight on: the segments share their ends.
+            let ends = Ends {
+                left: self.vertex(p + a * self.half),
+                right: self.vertex(p - a * self.half),
+            };
+            return Joint {
+                incoming: ends,
+                outgoing: ends,
+            };
+        }
+
+        // Both segments end at the same point on the inside of the turn,
+        // where their edges cross. Past a very sharp turn that point is kept
+        // within four half weights, as the segments would be too short anyway.
+        let side = if turn > 0.0 { 1.0 } else { -1.0 };
+        let inner = self.vertex(p + side * bisector * self.half * ratio.min(4.0));
+        let outer_in = self.vertex(p - side * a * self.half);
+        let outer_out = self.vertex(p - side * b * self.half);
+
+        match pen.join {
+            StrokeJoin::Miter if ratio <= pen.miter_limit => {
+                let tip = self.vertex(p - side * bisector * self.half * ratio);
+                self.indices
+                    .extend([inner, outer_in, tip, inner, tip, outer_out]);
+            }
+            StrokeJoin::Miter | StrokeJoin::Bevel => {
+                self.indices.extend([inner, outer_in, outer_out]);
+            }
+            StrokeJoin::Round => {
+                let (from, to) = (-side * a, -side * b);
+                self.fan(inner, p, from.to_angle(), from.angle_to(to));
+            }
+        }
+
+        let inside = Ends {
+            left: inner,
+            right: inner,
+        };
+        let (incoming, outgoing) = if turn > 0.0 {
+            // A left turn, with the inside on the left.
+            (
+                Ends {
+                    right: outer_in,
+                    ..inside
+                },
+                Ends {
+                    right: outer_out,
+                    ..inside
+                },
+            )
+        } else {
+            (
+                Ends {
+                    left: outer_in,
+                    ..inside
+                },
+                Ends {
+                    left: outer_out,
+                    ..inside
+                },
+            )
+        };
+        Joint { incoming, outgoing }
+    }
+}
@@ -0,0 +1,686 @@
+//! `begin_record()`/`end_record()`: everything drawn in between is also written
+//! to an SVG file, as vector shapes rather than the flattened triangles on screen.
+
+use std::fmt::Write;
+
+use bevy::math::Affine2;
+use bevy::prelude::*;
+use bevy::text::cosmic_text::ttf_parser::{Face, name_id};
+
+use super::blend::BlendMode;
+use super::image::{ImageMode, destination};
+use super::stroke::{StrokeCap, StrokeJoin};
+use super::text::{QueuedText, TextAlign, first_baseline};
+use super::{ArcMode, ProcessingCommand, ShapeKind, SketchState, Style, with_state};
+
+/// The file formats `begin_record()` can write.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RecordFormat {
+    Svg,
+}
+
+/// Commands captured since `begin_record()`.
+#[derive(Clone)]
+pub(super) struct Recording {
+    path: String,
+    commands: Vec<ProcessingCommand>,
+    /// Where the canvas's commands for this frame start being recorded.
+    from: usize,
+}
+
+/// Starts copying everything drawn, across frames, until `end_record()` writes
+/// it to `path`. Only what is shown is recorded, not the draws thrown away
+/// catching up with a `frame_rate()` above the window's. Images are linked by
+/// their path under `assets/`, not embedded.
+pub fn begin_record(format: RecordFormat, path: &str) {
+    let RecordFormat::Svg = format;
+    with_state(|s| {
+        s.recording = Some(Recording {
+            path: path.to_owned(),
+            commands: Vec::new(),
+            from: s.commands.len(),
+        })
+    });
+}
+/// Writes out what was drawn since `begin_record()`.
+pub fn end_record() {
+    let document = with_state(|s| {
+        record_frame(s);
+        let recording = s.recording.take()?;
+        Some((document(&recording.commands, s), recording.path))
+    });
+    let Some((document, path)) = document else {
+        warn!("end_record() called without begin_record()");
+        return;
+    };
+    if let Err(err) = std::fs::write(&path, document) {
+        warn!("could not write {path}: {err}");
+    }
+}
+
+/// Copies a canvas's commands into its recording, if one is running, as they
+/// are taken to be shown.
+pub(super) fn record_frame(state: &mut SketchState) {
+    if let Some(recording) = &mut state.recording {
+        let from = recording.from.min(state.commands.len());
+        recording
+            .commands
+            .extend_from_slice(&state.commands[from..]);
+        recording.from = 0;
+    }
+}
+
+/// Starts recording this frame's commands from `kept` on, where `run_draws`
+/// has just cut them back to start another draw.
+pub(super) fn rewind_recording(state: &mut SketchState, kept: usize) {
+    if let Some(recording) = &mut state.recording {
+        recording.from = recording.from.min(kept);
+    }
+}
+
+/// A coordinate with at most three decimals, without trailing zeros.
+fn num(v: f32) -> String {
+    decimals(v, 3)
+}
+
+fn decimals(v: f32, places: usize) -> String {
+    let s = format!("{v:.places$}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".into() } else { s.into() }
+}
+
+fn points(points: &[Vec2]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{},{}", num(p.x), num(p.y)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// `#rrggbb` and, for translucent colors, a separate opacity, since SVG 1.1
+/// has no alpha in color values.
+fn paint(attribute: &str, color: Color) -> String {
+    let [r, g, b, a] = color.to_srgba().to_u8_array();
+    let mut out = format!(" {attribute}=\"#{r:02x}{g:02x}{b:02x}\"");
+    if a < 255 {
+        let _ = write!(out, r#" {attribute}-opacity="{}""#, num(a as f32 / 255.0));
+    }
+    out
+}
+
+/// Fill, stroke and blend attributes for `style`.
+fn style_attributes(style: &Style) -> String {
+    let mut out = match style.fill {
+        Some(color) => paint("fill", color),
+        None => r#" fill="none""#.into(),
+    };
+    if let Some(color) = style.stroke {
+        out += &paint("stroke", color);
+        // Processing's SQUARE and PROJECT are SVG's butt and square.
+        let cap = match style.pen.cap {
+            StrokeCap::Round => "round",
+            StrokeCap::Square => "butt",
+            StrokeCap::Project => "square",
+        };
+        let join = match style.pen.join {
+            StrokeJoin::Miter => "miter",
+            StrokeJoin::Bevel => "bevel",
+            StrokeJoin::Round => "round",
+        };
+        let _ = write!(
+            out,
+            r#" stroke-width="{}" stroke-linecap="{cap}" stroke-linejoin="{join}" stroke-miterlimit="{}""#,
+            num(style.stroke_weight),
+            num(style.pen.miter_limit)
+        );
+    }
+    // CSS has no subtracting mode, so SUBTRACT is recorded as plain blending.
+    let blend = match style.blend {
+        BlendMode::Blend | BlendMode::Subtract => None,
+        BlendMode::Add => Some("plus-lighter"),
+        BlendMode::Lightest => Some("lighten"),
+        BlendMode::Darkest => Some("darken"),
+        BlendMode::Multiply => Some("multiply"),
+        BlendMode::Screen => Some("screen"),
+    };
+    if let Some(blend) = blend {
+        let _ = write!(out, r#" style="mix-blend-mode:{blend}""#);
+    }
+    out
+}
+
+fn transform_attribute(transform: &Affine2) -> String {
+    if *transform == Affine2::IDENTITY {
+        return String::new();
+    }
+    let (x, y, t) = (
+        transform.matrix2.x_axis,
+        transform.matrix2.y_axis,
+        transform.translation,
+    );
+    // Rotations and scales need more precision than coordinates.
+    format!(
+        r#" transform="matrix({} {} {} {} {} {})""#,
+        decimals(x.x, 6),
+        decimals(x.y, 6),
+        decimals(y.x, 6),
+        decimals(y.y, 6),
+        num(t.x),
+        num(t.y)
+    )
+}
+
+/// One `M … L …` subpath per contour.
+fn path_data(contours: &[Vec<Vec2>], close: impl Fn(usize) -> bool) -> String {
+    let mut d = String::new();
+    for (i, contour) in contours.iter().enumerate() {
+        for (j, p) in contour.iter().enumerate() {
+            let op = if j == 0 { 'M' } else { 'L' };
+            let _ = write!(d, "{op}{},{} ", num(p.x), num(p.y));
+        }
+        if close(i) && !contour.is_empty() {
+            d.push_str("Z ");
+        }
+    }
+    d.trim_end().to_owned()
+}
+
+/// An elliptical arc as SVG `A` segments of at most half a turn each, since a
+/// single segment can't describe a full ellipse.
+fn arc_data(
+    center: Vec2,
+    radii: Vec2,
+    start: f32,
+    stop: f32,
+    mode: ArcMode,
+    closed: bool,
+) -> String {
+    let at = |a: f32| center + Vec2::new(a.cos(), a.sin()) * radii;
+    let sweep = stop - start;
+    let pieces = (sweep.abs() / std::f32::consts::PI).ceil().max(1.0) as usize;
+    let from = at(start);
+    let mut d = if mode == ArcMode::Pie && closed {
+        format!(
+            "M{},{} L{},{}",
+            num(center.x),
+            num(center.y),
+            num(from.x),
+            num(from.y)
+        )
+    } else {
+        format!("M{},{}", num(from.x), num(from.y))
+    };
+    for i in 1..=pieces {
+        let p = at(start + sweep * i as f32 / pieces as f32);
+        let _ = write!(
+            d,
+            " A{},{} 0 0 {} {},{}",
+            num(radii.x.abs()),
+            num(radii.y.abs()),
+            if sweep >= 0.0 { 1 } else { 0 },
+            num(p.x),
+            num(p.y)
+        );
+    }
+    if closed {
+        d.push_str(" Z");
+    }
+    d
+}
+
+fn element(out: &mut String, tag: &str, geometry: &str, style: &Style, transform: &Affine2) {
+    let _ = writeln!(
+        out,
+        "  <{tag} {geometry}{}{}/>",
+        style_attributes(style),
+        transform_attribute(transform)
+    );
+}
+
+/// The family name stored in a font file, for the `font-family` attribute.
+fn family_name(data: &[u8]) -> Option<String> {
+    let face = Face::parse(data, 0).ok()?;
+    face.names()
+        .into_iter()
+        .filter(|name| name.name_id == name_id::FAMILY && name.is_unicode())
+        .find_map(|name| name.to_string())
+}
+
+fn write_text(out: &mut String, text: &QueuedText, state: &SketchState) {
+    let data = state.font_data.get(&text.text_style.font.id());
+    let face = data.and_then(|data| Face::parse(data, 0).ok());
+    let family = data
+        .and_then(|data| family_name(data))
+        .unwrap_or_else(|| "sans-serif".into());
+    let anchor = match text.text_style.align {
+        TextAlign::Left => "start",
+        TextAlign::Center => "middle",
+        TextAlign::Right => "end",
+    };
+    let first = first_baseline(text, face.as_ref());
+    let _ = writeln!(
+        out,
+        r#"  <text font-family="{}" font-size="{}" text-anchor="{anchor}"{}{}>"#,
+        escape(&family),
+        num(text.text_style.size),
+        paint("fill", text.color),
+        transform_attribute(&text.transform),
+    );
+    for (i, line) in text.content.lines().enumerate() {
+        let _ = writeln!(
+            out,
+            r#"    <tspan x="{}" y="{}">{}</tspan>"#,
+            num(text.position.x),
+            num(first + i as f32 * text.text_style.leading()),
+            escape(line)
+        );
+    }
+    out.push_str("  </text>\n");
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_image(
+    out: &mut String,
+    image: &Handle<Image>,
+    position: Vec2,
+    extent: Option<Vec2>,
+    source: Option<Rect>,
+    mode: ImageMode,
+    tint: Color,
+    transform: &Affine2,
+    state: &SketchState,
+) {
+    let (Some(path), Some(natural)) = (
+        image.path(),
+        state.images.get(&image.id()).map(|image| image.size_f32()),
+    ) else {
+        return;
+    };
+    let source = source.unwrap_or(Rect::from_corners(Vec2::ZERO, natural));
+    let dest = destination(position, extent, mode, source.size());
+    let opacity = tint.alpha();
+    // `transform` is not allowed on a nested <svg>, so a group carries it.
+    let group = *transform != Affine2::IDENTITY;
+    if group {
+        let _ = writeln!(out, "  <g{}>", transform_attribute(transform));
+    }
+    // A nested viewport crops the sub-image and stretches it over `dest`.
+    let _ = writeln!(
+        out,
+        r#"  <svg x="{}" y="{}" width="{}" height="{}" viewBox="{} {} {} {}" preserveAspectRatio="none"{}>"#,
+        num(dest.min.x),
+        num(dest.min.y),
+        num(dest.width()),
+        num(dest.height()),
+        num(source.min.x),
+        num(source.min.y),
+        num(source.width()),
+        num(source.height()),
+        if opacity < 1.0 {
+            format!(r#" opacity="{}""#, num(opacity))
+        } else {
+            String::new()
+        },
+    );
+    let _ = writeln!(
+        out,
+        r#"    <image width="{}" height="{}" xlink:href="assets/{}"/>"#,
+        num(natural.x),
+        num(natural.y),
+        escape(&path.path().to_string_lossy())
+    );
+    out.push_str("  </svg>\n");
+    if group {
+        out.push_str("  </g>\n");
+    }
+}
+
+fn write_command(out: &mut String, cmd: &ProcessingCommand, state: &SketchState) {
+    match cmd {
+        ProcessingCommand::Point {
+            x,
+            y,
+            style,
+            transform,
+        } => write_dot(out, Vec2::new(*x, *y), style, transform),
+        ProcessingCommand::Line {
+            x1,
+            y1,
+            x2,
+            y2,
+            style,
+            transform,
+        } => {
+            let style = Style {
+                fill: None,
+                ..*style
+            };
+            let geometry = format!(
+                r#"x1="{}" y1="{}" x2="{}" y2="{}""#,
+                num(*x1),
+                num(*y1),
+                num(*x2),
+                num(*y2)
+            );
+            element(out, "line", &geometry, &style, transform);
+        }
+        ProcessingCommand::Rect {
+            x,
+            y,
+            w,
+            h,
+            radii,
+            style,
+            transform,
+        } => {
+            let [tl, tr, br, bl] = *radii;
+            if radii.iter().all(|r| *r == tl) {
+                let mut geometry = format!(
+                    r#"x="{}" y="{}" width="{}" height="{}""#,
+                    num(*x),
+                    num(*y),
+                    num(*w),
+                    num(*h)
+                );
+                if tl > 0.0 {
+                    let _ = write!(geometry, r#" rx="{}""#, num(tl));
+                }
+                element(out, "rect", &geometry, style, transform);
+            } else {
+                // Each corner an arc, or a sharp turn where its radius is 0.
+                let (right, bottom) = (x + w, y + h);
+                let corner = |r: f32, x: f32, y: f32| {
+                    format!(" A{},{} 0 0 1 {},{}", num(r), num(r), num(x), num(y))
+                };
+                let d = format!(
+                    "M{},{} H{}{} V{}{} H{}{} V{}{} Z",
+                    num(x + tl),
+                    num(*y),
+                    num(right - tr),
+                    corner(tr, right, y + tr),
+                    num(bottom - br),
+                    corner(br, right - br, bottom),
+                    num(x + bl),
+                    corner(bl, *x, bottom - bl),
+                    num(y + tl),
+                    corner(tl, x + tl, *y),
+                );
+                element(out, "path", &format!(r#"d="{d}""#), style, transform);
+            }
+        }
+        ProcessingCommand::Ellipse {
+            cx,
+            cy,
+            w,
+            h,
+            style,
+            transform,
+        } => {
+            let geometry = format!(
+                r#"cx="{}" cy="{}" rx="{}" ry="{}""#,
+                num(*cx),
+                num(*cy),
+                num(w.abs() * 0.5),
+                num(h.abs() * 0.5)
+            );
+            element(out, "ellipse", &geometry, style, transform);
+        }
+        ProcessingCommand::Triangle {
+            x1,
+            y1,
+            x2,
+            y2,
+            x3,
+            y3,
+            style,
+            transform,
+        } => {
+            let corners = [
+                Vec2::new(*x1, *y1),
+                Vec2::new(*x2, *y2),
+                Vec2::new(*x3, *y3),
+            ];
+            let geometry = format!(r#"points="{}""#, points(&corners));
+            element(out, "polygon", &geometry, style, transform);
+        }
+        ProcessingCommand::Quad {
+            corners,
+            style,
+            transform,
+        } => {
+            let geometry = format!(r#"points="{}""#, points(corners));
+            element(out, "polygon", &geometry, style, transform);
+        }
+        ProcessingCommand::Arc {
+            cx,
+            cy,
+            w,
+            h,
+            start,
+            stop,
+            mode,
+            style,
+            transform,
+        } => {
+            let center = Vec2::new(*cx, *cy);
+            let radii = Vec2::new(*w, *h) * 0.5;
+            let stop = stop.min(start + std::f32::consts::TAU);
+            // An open arc is filled like a chord but its outline stays open.
+            if mode == &ArcMode::Open && style.fill.is_some() && style.stroke.is_some() {
+                let fill = Style {
+                    stroke: None,
+                    ..*style
+                };
+                let outline = Style {
+                    fill: None,
+                    ..*style
+                };
+                let d = arc_data(center, radii, *start, stop, *mode, true);
+                element(out, "path", &format!(r#"d="{d}""#), &fill, transform);
+                let d = arc_data(center, radii, *start, stop, *mode, false);
+                element(out, "path", &format!(r#"d="{d}""#), &outline, transform);
+            } else {
+                let closed = *mode != ArcMode::Open || style.stroke.is_none();
+                let d = arc_data(center, radii, *start, stop, *mode, closed);
+                element(out, "path", &format!(r#"d="{d}""#), style, transform);
+            }
+        }
+        ProcessingCommand::Shape {
+            kind,
+            contours,
+            close,
+            style,
+            transform,
+        } => write_shape(out, *kind, contours, *close, style, transform),
+        ProcessingCommand::Image {
+            image,
+            position,
+            extent,
+            source,
+            mode,
+            tint,
+            transform,
+            ..
+        } => write_image(
+            out, image, *position, *extent, *source, *mode, *tint, transform, state,
+        ),
+        ProcessingCommand::Text {
+            content,
+            x,
+            y,
+            text_style,
+            style,
+            transform,
+        } => {
+            if let Some(color) = style.fill {
+                let text = QueuedText {
+                    content: content.clone(),
+                    position: Vec2::new(*x, *y),
+                    text_style: text_style.clone(),
+                    color,
+                    transform: *transform,
+                    z: 0.0,
+                };
+                write_text(out, &text, state);
+            }
+        }
+        ProcessingCommand::Background { color } => {
+            let _ = writeln!(
+                out,
+                r#"  <rect width="100%" height="100%"{}/>"#,
+                paint("fill", *color)
+            );
+        }
+    }
+}
+
+/// Mirrors `MeshBuilder::dot`: a circle as wide as the stroke weight.
+fn write_dot(out: &mut String, p: Vec2, style: &Style, transform: &Affine2) {
+    let Some(color) = style.stroke else {
+        return;
+    };
+    let dot = Style {
+        fill: Some(color),
+        stroke: None,
+        ..*style
+    };
+    let geometry = format!(
+        r#"cx="{}" cy="{}" r="{}""#,
+        num(p.x),
+        num(p.y),
+        num(style.stroke_weight * 0.5)
+    );
+    element(out, "circle", &geometry, &dot, transform);
+}
+
+/// Mirrors `MeshBuilder::vertex_shape`: one element per primitive.
+fn write_shape(
+    out: &mut String,
+    kind: ShapeKind,
+    contours: &[Vec<Vec2>],
+    close: bool,
+    style: &Style,
+    transform: &Affine2,
+) {
+    let v = &contours[0];
+    let polygon = |out: &mut String, corners: &[Vec2]| {
+        let geometry = format!(r#"points="{}""#, points(corners));
+        element(out, "polygon", &geometry, style, transform);
+    };
+    match kind {
+        ShapeKind::Polygon => {
+            // Holes are cut out whichever way they wind.
+            let d = path_data(contours, |i| i > 0 || close);
+            let geometry = format!(r#"d="{d}" fill-rule="evenodd""#);
+            element(out, "path", &geometry, style, transform);
+        }
+        ShapeKind::Points => {
+            for &p in v {
+                write_dot(out, p, style, transform);
+            }
+        }
+        ShapeKind::Lines => {
+            let line = Style {
+                fill: None,
+                ..*style
+            };
+            for pair in v.chunks_exact(2) {
+                let geometry = format!(
+                    r#"x1="{}" y1="{}" x2="{}" y2="{}""#,
+                    num(pair[0].x),
+                    num(pair[0].y),
+                    num(pair[1].x),
+                    num(pair[1].y)
+                );
+                element(out, "line", &geometry, &line, transform);
+            }
+        }
+        ShapeKind::Triangles => v.chunks_exact(3).for_each(|tri| polygon(out, tri)),
+        ShapeKind::TriangleStrip => v.windows(3).for_each(|tri| polygon(out, tri)),
+        ShapeKind::TriangleFan => {
+            for i in 1..v.len().saturating_sub(1) {
+                polygon(out, &[v[0], v[i], v[i + 1]]);
+            }
+        }
+        ShapeKind::Quads => v.chunks_exact(4).for_each(|quad| polygon(out, quad)),
+        ShapeKind::QuadStrip => {
+            for i in (0..v.len().saturating_sub(3)).step_by(2) {
+                polygon(out, &[v[i], v[i + 1], v[i + 3], v[i + 2]]);
+            }
+        }
+    }
+}
+
+fn document(commands: &[ProcessingCommand], state: &SketchState) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = num(state.surface.size.x),
+        h = num(state.surface.size.y)
+    );
+    // SVG paints in document order, so each `layer()` goes over the ones below.
+    let mut commands: Vec<_> = commands.iter().collect();
+    commands.sort_by_key(|cmd| cmd.layer());
+    for cmd in commands {
+        write_command(&mut out, cmd, state);
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pacing::{frame_rate, run_draws};
+    use super::super::{point, run_user};
+    use super::*;
+
+    fn recorded(state: &SketchState) -> Vec<f32> {
+        let recording = state.recording.as_ref().unwrap();
+        recording
+            .commands
+            .iter()
+            .map(|cmd| match cmd {
+                ProcessingCommand::Point { x, .. } => *x,
+                _ => panic!("unexpected command"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn only_shown_frames_are_recorded() {
+        let mut state = SketchState::default();
+        run_user(&mut state, || {
+            point(0.0, 0.0);
+            begin_record(RecordFormat::Svg, "unused.svg");
+            point(1.0, 0.0);
+            frame_rate(100.0);
+        });
+        state.pacing.schedule(0.0);
+        state.pacing.schedule(0.035);
+        let mut runs = 0;
+        run_draws(&mut state, || {
+            runs += 1;
+            point(10.0 + runs as f32, 0.0);
+        });
+        assert_eq!(runs, 3);
+        // What `rasterize_frame` does with a frame it shows.
+        record_frame(&mut state);
+        state.commands.clear();
+
+        // Not what came before `begin_record()`, nor the first two draws.
+        assert_eq!(recorded(&state), [1.0, 13.0]);
+
+        run_user(&mut state, || point(20.0, 0.0));
+        record_frame(&mut state);
+        assert_eq!(recorded(&state), [1.0, 13.0, 20.0]);
+    }
+}
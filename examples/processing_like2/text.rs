@@ -0,0 +1,470 @@
+//! `text()` and its settings. Text is drawn with pooled `Text2d` entities,
+//! reused from frame to frame and only changed where their text did, so Bevy
+//! doesn't lay out text that stays the same again.
+
+use bevy::math::Affine2;
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy::sprite::Anchor;
+use bevy::text::LineHeight;
+use bevy::text::cosmic_text::ttf_parser::{Face, OutlineBuilder};
+
+use super::curves::{flatten_cubic, flatten_quadratic};
+use super::{ProcessingCommand, SketchState, matrix, send, style, transform_scale, with_state};
+
+/// A font returned by `load_font()`; the default is Bevy's built-in font.
+#[derive(Clone, Default)]
+pub struct PFont(Handle<Font>);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Which part of the text sits on the `y` passed to `text()`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TextBaseline {
+    Top,
+    Center,
+    #[default]
+    Baseline,
+    Bottom,
+}
+
+/// Text settings, captured into every `text()` call like `Style` is for shapes.
+#[derive(Clone)]
+pub(super) struct TextStyle {
+    pub font: Handle<Font>,
+    pub size: f32,
+    /// Distance between baselines; `None` follows the size.
+    leading: Option<f32>,
+    pub align: TextAlign,
+    baseline: TextBaseline,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            font: Handle::default(),
+            size: 12.0,
+            leading: None,
+            align: TextAlign::Left,
+            baseline: TextBaseline::Baseline,
+        }
+    }
+}
+
+impl TextStyle {
+    pub fn leading(&self) -> f32 {
+        self.leading.unwrap_or(self.size * 1.25)
+    }
+}
+
+/// Loads a font from `assets/`. It can be used right away, but is drawn with the
+/// default font and measures as zero width until it has finished loading.
+pub fn load_font(path: &str) -> PFont {
+    with_state(|s| match &s.asset_server {
+        Some(assets) => PFont(assets.load(path.to_owned())),
+        None => {
+            warn!("load_font() called outside a running sketch");
+            PFont(Handle::default())
+        }
+    })
+}
+pub fn text_font(font: &PFont) {
+    with_state(|s| s.text.font = font.0.clone());
+}
+pub fn text_size(size: f32) {
+    with_state(|s| s.text.size = size);
+}
+/// Sets the distance between the baselines of consecutive lines.
+pub fn text_leading(leading: f32) {
+    with_state(|s| s.text.leading = Some(leading));
+}
+pub fn text_align(align: TextAlign, baseline: TextBaseline) {
+    with_state(|s| {
+        s.text.align = align;
+        s.text.baseline = baseline;
+    });
+}
+/// Width of the widest line of `content` in the current font and size.
+pub fn text_width(content: &str) -> f32 {
+    with_state(|s| {
+        let data = s.font_data.get(&s.text.font.id())?;
+        let face = Face::parse(data, 0).ok()?;
+        Some(measure_width(&face, s.text.size, content))
+    })
+    .unwrap_or(0.0)
+}
+/// Draws `content` at `(x, y)` in the fill color; `\n` starts a new line.
+pub fn text(content: impl Into<String>, x: f32, y: f32) {
+    send(ProcessingCommand::Text {
+        content: content.into(),
+        x,
+        y,
+        text_style: with_state(|s| s.text.clone()),
+        style: style(),
+        transform: matrix(),
+    });
+}
+
+fn measure_width(face: &Face, size: f32, content: &str) -> f32 {
+    let scale = size / face.units_per_em() as f32;
+    content
+        .lines()
+        .map(|line| {
+            line.chars()
+                .filter_map(|c| face.glyph_index(c))
+                .filter_map(|g| face.glyph_hor_advance(g))
+                .map(|advance| advance as f32 * scale)
+                .sum::<f32>()
+        })
+        .fold(0.0, f32::max)
+}
+
+/// A `text()` call waiting to be assigned to a pooled entity.
+pub(super) struct QueuedText {
+    pub content: String,
+    pub position: Vec2,
+    pub text_style: TextStyle,
+    pub color: Color,
+    pub transform: Affine2,
+    /// Depth among the frame's shape batches, images and text, from 0 to 1.
+    pub z: f32,
+}
+
+/// A canvas's text collected by `rasterize_frame` for `update_text` to display.
+#[derive(Component, Default)]
+pub(super) struct TextQueue(pub Vec<QueuedText>);
+
+/// The text entities of a canvas, reused from frame to frame.
+#[derive(Component, Default)]
+pub(super) struct TextPool(Vec<Entity>);
+
+/// Where the top of the laid-out block must go, relative to the `y` given to
+/// `text()`, so that the requested `TextBaseline` lands on it. Bevy centres the
+/// glyphs' ascent and descent inside each line box.
+fn top_offset(text: &QueuedText, face: Option<&Face>) -> f32 {
+    let (ascent, descent) = vertical_metrics(face, text.text_style.size);
+    let leading = text.text_style.leading();
+    let padding = (leading - (ascent + descent)) * 0.5;
+    let lines = text.content.lines().count().max(1) as f32;
+    match text.text_style.baseline {
+        TextBaseline::Top => -padding,
+        TextBaseline::Center => -lines * leading * 0.5,
+        TextBaseline::Baseline => -(padding + ascent),
+        TextBaseline::Bottom => -lines * leading + padding,
+    }
+}
+
+/// Ascent and descent in pixels, guessed from the size while the font loads.
+fn vertical_metrics(face: Option<&Face>, size: f32) -> (f32, f32) {
+    match face {
+        Some(face) => {
+            let scale = size / face.units_per_em() as f32;
+            (
+                face.ascender() as f32 * scale,
+                -face.descender() as f32 * scale,
+            )
+        }
+        None => (size * 0.8, size * 0.2),
+    }
+}
+
+/// The canvas y of the first line's baseline, before the sketch matrix applies.
+pub(super) fn first_baseline(text: &QueuedText, face: Option<&Face>) -> f32 {
+    let (ascent, descent) = vertical_metrics(face, text.text_style.size);
+    let padding = (text.text_style.leading() - (ascent + descent)) * 0.5;
+    text.position.y + top_offset(text, face) + padding + ascent
+}
+
+/// Collects glyph outlines as flattened contours, placing font units at `origin`.
+struct Outliner {
+    contours: Vec<Vec<Vec2>>,
+    origin: Vec2,
+    scale: f32,
+    tolerance: f32,
+}
+
+impl Outliner {
+    fn point(&self, x: f32, y: f32) -> Vec2 {
+        // Font units point up; the canvas points down.
+        self.origin + Vec2::new(x, -y) * self.scale
+    }
+    fn last(&self) -> Vec2 {
+        self.contours
+            .last()
+            .and_then(|c| c.last())
+            .copied()
+            .unwrap_or(self.origin)
+    }
+}
+
+impl OutlineBuilder for Outliner {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        self.contours.push(vec![p]);
+    }
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        if let Some(contour) = self.contours.last_mut() {
+            contour.push(p);
+        }
+    }
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (from, c, to) = (self.last(), self.point(x1, y1), self.point(x, y));
+        if let Some(contour) = self.contours.last_mut() {
+            flatten_quadratic(contour, from, c, to, self.tolerance);
+        }
+    }
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let from = self.last();
+        let (c1, c2, to) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        if let Some(contour) = self.contours.last_mut() {
+            flatten_cubic(contour, from, c1, c2, to, self.tolerance);
+        }
+    }
+    fn close(&mut self) {}
+}
+
+/// The glyph outlines of `text` in canvas coordinates, laid out the way
+/// `update_text` places them, for the software renderer to fill with the
+/// non-zero rule, given the bytes of its font file. Empty until the font has loaded.
+pub(super) fn glyph_contours(text: &QueuedText, font: Option<&[u8]>) -> Vec<Vec<Vec2>> {
+    let Some(face) = font.and_then(|data| Face::parse(data, 0).ok()) else {
+        return Vec::new();
+    };
+    let style = &text.text_style;
+    let scale = style.size / face.units_per_em() as f32;
+    let first = first_baseline(text, Some(&face));
+    let mut outliner = Outliner {
+        contours: Vec::new(),
+        origin: Vec2::ZERO,
+        scale,
+        tolerance: 0.25 / transform_scale(&text.transform),
+    };
+    for (i, line) in text.content.lines().enumerate() {
+        let width = measure_width(&face, style.size, line);
+        let x = match style.align {
+            TextAlign::Left => text.position.x,
+            TextAlign::Center => text.position.x - width * 0.5,
+            TextAlign::Right => text.position.x - width,
+        };
+        outliner.origin = Vec2::new(x, first + i as f32 * style.leading());
+        for glyph in line.chars().filter_map(|c| face.glyph_index(c)) {
+            face.outline_glyph(glyph, &mut outliner);
+            let advance = face.glyph_hor_advance(glyph).unwrap_or(0);
+            outliner.origin.x += advance as f32 * scale;
+        }
+    }
+    for point in outliner.contours.iter_mut().flatten() {
+        *point = text.transform.transform_point2(*point);
+    }
+    outliner.contours
+}
+
+/// Maps the text's own y-up space through the sketch matrix onto the world.
+fn text_transform(transform: &Affine2, origin: Vec2, size: Vec2, z: f32) -> Transform {
+    let flip = Affine2::from_scale(Vec2::new(1.0, -1.0));
+    let canvas_to_world = Affine2::from_translation(Vec2::new(-size.x, size.y) * 0.5) * flip;
+    let world = canvas_to_world * *transform * Affine2::from_translation(origin) * flip;
+    let (scale, angle, translation) = world.to_scale_angle_translation();
+    Transform {
+        translation: translation.extend(z),
+        rotation: Quat::from_rotation_z(angle),
+        scale: scale.extend(1.0),
+    }
+}
+
+type TextParts = (
+    &'static mut Text2d,
+    &'static mut TextFont,
+    &'static mut TextColor,
+    &'static mut TextLayout,
+    &'static mut Anchor,
+    &'static mut Transform,
+    &'static mut Visibility,
+);
+
+/// Shows each canvas's queued text, reusing entities from earlier frames.
+pub(super) fn update_text(
+    mut commands: Commands,
+    mut canvases: Query<(
+        Entity,
+        &SketchState,
+        &mut TextQueue,
+        &mut TextPool,
+        Option<&RenderLayers>,
+    )>,
+    fonts: Res<Assets<Font>>,
+    mut entities: Query<TextParts>,
+) {
+    for (canvas, state, mut queue, mut pool, layers) in &mut canvases {
+        let pooled = pool.0.len();
+        show_text(
+            &mut commands,
+            canvas,
+            state.surface.size,
+            &mut queue,
+            &mut pool.0,
+            &fonts,
+            &mut entities,
+        );
+        // New text goes on the canvas's render layer, as its meshes do.
+        if let Some(layers) = layers {
+            for &entity in &pool.0[pooled..] {
+                commands.entity(entity).insert(layers.clone());
+            }
+        }
+    }
+}
+
+fn show_text(
+    commands: &mut Commands,
+    canvas: Entity,
+    size: Vec2,
+    queue: &mut TextQueue,
+    pool: &mut Vec<Entity>,
+    fonts: &Assets<Font>,
+    entities: &mut Query<TextParts>,
+) {
+    let count = queue.0.len();
+    for (i, text) in queue.0.drain(..).enumerate() {
+        let face = fonts
+            .get(&text.text_style.font)
+            .and_then(|font| Face::parse(&font.data, 0).ok());
+        let origin = Vec2::new(
+            text.position.x,
+            text.position.y + top_offset(&text, face.as_ref()),
+        );
+        let (justify, anchor) = match text.text_style.align {
+            TextAlign::Left => (JustifyText::Left, Anchor::TopLeft),
+            TextAlign::Center => (JustifyText::Center, Anchor::TopCenter),
+            TextAlign::Right => (JustifyText::Right, Anchor::TopRight),
+        };
+        let font = TextFont {
+            font: text.text_style.font.clone(),
+            font_size: text.text_style.size,
+            line_height: LineHeight::Px(text.text_style.leading()),
+            ..default()
+        };
+        let transform = text_transform(&text.transform, origin, size, text.z);
+        match pool.get(i) {
+            Some(&entity) => {
+                let Ok((mut t, mut f, mut c, mut layout, mut a, mut tf, mut visibility)) =
+                    entities.get_mut(entity)
+                else {
+                    continue;
+                };
+                // Assigning marks a component changed, and a changed text
+                // component makes Bevy lay the text out again.
+                if t.0 != text.content {
+                    t.0 = text.content;
+                }
+                if !same_font(&f, &font) {
+                    *f = font;
+                }
+                c.set_if_neq(TextColor(text.color));
+                if layout.justify != justify {
+                    layout.justify = justify;
+                }
+                a.set_if_neq(anchor);
+                tf.set_if_neq(transform);
+                visibility.set_if_neq(Visibility::Inherited);
+            }
+            None => {
+                let entity = commands
+                    .spawn((
+                        Text2d::new(text.content),
+                        font,
+                        TextColor(text.color),
+                        TextLayout::new_with_justify(justify),
+                        anchor,
+                        transform,
+                        ChildOf(canvas),
+                    ))
+                    .id();
+                pool.push(entity);
+            }
+        }
+    }
+    for &entity in pool.iter().skip(count) {
+        if let Ok((.., mut visibility)) = entities.get_mut(entity) {
+            visibility.set_if_neq(Visibility::Hidden);
+        }
+    }
+}
+
+/// `TextFont` has no `PartialEq`; these are the fields `show_text` sets.
+fn same_font(a: &TextFont, b: &TextFont) -> bool {
+    let height = |font: &TextFont| match font.line_height {
+        LineHeight::Px(px) => Some(px),
+        LineHeight::RelativeToFont(_) => None,
+    };
+    a.font == b.font && a.font_size == b.font_size && height(a) == height(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::component::{ComponentTicks, Tick};
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn queued(content: &str, color: Color) -> QueuedText {
+        QueuedText {
+            content: content.into(),
+            position: Vec2::new(10.0, 20.0),
+            text_style: TextStyle::default(),
+            color,
+            transform: Affine2::IDENTITY,
+            z: 0.5,
+        }
+    }
+
+    #[test]
+    fn text_is_only_changed_when_it_changes() {
+        let mut world = World::new();
+        world.init_resource::<Assets<Font>>();
+        let canvas = world
+            .spawn((
+                SketchState::default(),
+                TextQueue::default(),
+                TextPool::default(),
+            ))
+            .id();
+        // When each part of the pooled entity was last changed.
+        let draw = |world: &mut World, content: &str, color: Color| -> [Tick; 5] {
+            world.get_mut::<TextQueue>(canvas).unwrap().0 = vec![queued(content, color)];
+            world.run_system_once(update_text).unwrap();
+            let pool = world.get::<TextPool>(canvas).unwrap();
+            assert_eq!(pool.0.len(), 1);
+            let entity = world.entity(pool.0[0]);
+            let changed = |ticks: Option<ComponentTicks>| ticks.unwrap().changed;
+            [
+                changed(entity.get_change_ticks::<Text2d>()),
+                changed(entity.get_change_ticks::<TextFont>()),
+                changed(entity.get_change_ticks::<TextColor>()),
+                changed(entity.get_change_ticks::<Transform>()),
+                changed(entity.get_change_ticks::<Visibility>()),
+            ]
+        };
+
+        let first = draw(&mut world, "hello", Color::WHITE);
+        assert_eq!(draw(&mut world, "hello", Color::WHITE), first);
+
+        let [text, font, color, transform, visibility] = draw(&mut world, "world", Color::WHITE);
+        assert_ne!(text, first[0]);
+        assert_eq!([font, color, transform, visibility], first[1..]);
+
+        let [_, font, color, transform, visibility] = draw(&mut world, "world", Color::BLACK);
+        assert_ne!(color, first[2]);
+        assert_eq!(
+            [font, transform, visibility],
+            [first[1], first[3], first[4]]
+        );
+    }
+}
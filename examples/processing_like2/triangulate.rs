@@ -0,0 +1,268 @@
+//! Ear-clipping triangulation for `begin_shape()` polygons, including holes
+//! added with `begin_contour()`.
+
+use bevy::math::Vec2;
+
+const EPSILON: f32 = 1e-6;
+
+fn signed_area(points: &[Vec2], ring: &[usize]) -> f32 {
+    let mut area = 0.0;
+    for i in 0..ring.len() {
+        let a = points[ring[i]];
+        let b = points[ring[(i + 1) % ring.len()]];
+        area += a.perp_dot(b);
+    }
+    area * 0.5
+}
+
+fn cross(a: Vec2, b: Vec2, c: Vec2) -> f32 {
+    (b - a).perp_dot(c - b)
+}
+
+/// True if `p` is inside triangle `abc` or on its edge `c-a`, the diagonal an
+/// ear clip would cut: a vertex there would be cut off from the polygon.
+fn inside_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    cross(a, b, p) > EPSILON && cross(b, c, p) > EPSILON && cross(c, a, p) >= -EPSILON
+}
+
+/// True if segments `p1-p2` and `q1-q2` cross at a point interior to both.
+fn segments_cross(p1: Vec2, p2: Vec2, q1: Vec2, q2: Vec2) -> bool {
+    let d1 = (p2 - p1).perp_dot(q1 - p1);
+    let d2 = (p2 - p1).perp_dot(q2 - p1);
+    let d3 = (q2 - q1).perp_dot(p1 - q1);
+    let d4 = (q2 - q1).perp_dot(p2 - q1);
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+fn crosses_ring(points: &[Vec2], ring: &[usize], from: Vec2, to: Vec2) -> bool {
+    (0..ring.len()).any(|i| {
+        let a = points[ring[i]];
+        let b = points[ring[(i + 1) % ring.len()]];
+        segments_cross(from, to, a, b)
+    })
+}
+
+/// Joins `hole` into `ring` through a bridge from the hole's rightmost vertex
+/// to the nearest ring vertex it can see.
+fn bridge_hole(points: &[Vec2], ring: &mut Vec<usize>, hole: &[usize], others: &[Vec<usize>]) {
+    let (m_pos, &m) = hole
+        .iter()
+        .enumerate()
+        .max_by(|a, b| points[*a.1].x.total_cmp(&points[*b.1].x))
+        .unwrap();
+    let from = points[m];
+
+    let mut candidates: Vec<usize> = (0..ring.len()).collect();
+    candidates.sort_by(|&a, &b| {
+        let da = points[ring[a]].distance_squared(from);
+        let db = points[ring[b]].distance_squared(from);
+        da.total_cmp(&db)
+    });
+    let visible = candidates.iter().copied().find(|&i| {
+        let to = points[ring[i]];
+        !crosses_ring(points, ring, from, to)
+            && !crosses_ring(points, hole, from, to)
+            && !others.iter().any(|o| crosses_ring(points, o, from, to))
+    });
+    let p_pos = visible.unwrap_or(candidates[0]);
+
+    let mut merged = Vec::with_capacity(ring.len() + hole.len() + 2);
+    merged.extend_from_slice(&ring[..=p_pos]);
+    merged.extend_from_slice(&hole[m_pos..]);
+    merged.extend_from_slice(&hole[..=m_pos]);
+    merged.extend_from_slice(&ring[p_pos..]);
+    *ring = merged;
+}
+
+/// Triangulates `outer` with every polygon in `holes` cut out of it.
+///
+/// The returned indices refer to `outer` followed by each hole in order, so the
+/// caller can upload the concatenated points as-is. Either winding is accepted.
+pub fn triangulate(outer: &[Vec2], holes: &[Vec<Vec2>]) -> Vec<u32> {
+    let mut points = outer.to_vec();
+    let mut ring: Vec<usize> = (0..outer.len()).collect();
+    if ring.len() < 3 {
+        return Vec::new();
+    }
+    if signed_area(&points, &ring) < 0.0 {
+        ring.reverse();
+    }
+
+    let mut hole_rings = Vec::new();
+    for hole in holes {
+        let start = points.len();
+        points.extend_from_slice(hole);
+        let mut hole_ring: Vec<usize> = (start..points.len()).collect();
+        if hole_ring.len() < 3 {
+            continue;
+        }
+        // Holes wind the opposite way so the bridged ring stays consistent.
+        if signed_area(&points, &hole_ring) > 0.0 {
+            hole_ring.reverse();
+        }
+        hole_rings.push(hole_ring);
+    }
+    // Rightmost holes first, so later bridges can't be cut by earlier ones.
+    hole_rings.sort_by(|a, b| {
+        let max_x = |r: &Vec<usize>| r.iter().map(|&i| points[i].x).fold(f32::MIN, f32::max);
+        max_x(b).total_cmp(&max_x(a))
+    });
+    for i in 0..hole_rings.len() {
+        bridge_hole(&points, &mut ring, &hole_rings[i], &hole_rings[i + 1..]);
+    }
+
+    let mut triangles = Vec::with_capacity((ring.len() - 2) * 3);
+    let mut i = 0;
+    let mut misses = 0;
+    while ring.len() > 3 {
+        let n = ring.len();
+        i %= n;
+        let (ia, ib, ic) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+        let (a, b, c) = (points[ia], points[ib], points[ic]);
+        let turn = cross(a, b, c);
+        if turn.abs() <= EPSILON {
+            // Collinear or zero-length: drop the vertex without emitting a triangle.
+            ring.remove(i);
+            misses = 0;
+            continue;
+        }
+        let is_ear = turn > 0.0
+            && !ring.iter().any(|&j| {
+                let p = points[j];
+                p != a && p != b && p != c && inside_triangle(p, a, b, c)
+            });
+        // Self-intersecting input can leave no ears; clip anyway rather than loop forever.
+        if is_ear || misses > n {
+            triangles.extend([ia as u32, ib as u32, ic as u32]);
+            ring.remove(i);
+            misses = 0;
+        } else {
+            i += 1;
+            misses += 1;
+        }
+    }
+    if let [a, b, c] = ring[..]
+        && cross(points[a], points[b], points[c]).abs() > EPSILON
+    {
+        triangles.extend([a as u32, b as u32, c as u32]);
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polygon(points: &[(f32, f32)]) -> Vec<Vec2> {
+        points.iter().map(|&(x, y)| Vec2::new(x, y)).collect()
+    }
+
+    /// Triangulates and returns how many triangles came out and their total area.
+    fn triangulated(outer: &[Vec2], holes: &[Vec<Vec2>]) -> (usize, f32) {
+        let points: Vec<Vec2> = outer
+            .iter()
+            .chain(holes.iter().flatten())
+            .copied()
+            .collect();
+        let indices = triangulate(outer, holes);
+        assert_eq!(indices.len() % 3, 0);
+        let area = indices
+            .chunks_exact(3)
+            .map(|t| {
+                let [a, b, c] = [t[0], t[1], t[2]].map(|i| points[i as usize]);
+                (b - a).perp_dot(c - a).abs() * 0.5
+            })
+            .sum();
+        (indices.len() / 3, area)
+    }
+
+    fn assert_triangulates(outer: &[Vec2], holes: &[Vec<Vec2>], count: usize, area: f32) {
+        let (got_count, got_area) = triangulated(outer, holes);
+        assert_eq!(got_count, count, "triangle count");
+        assert!(
+            (got_area - area).abs() < 1e-4,
+            "triangles cover {got_area}, expected {area}"
+        );
+    }
+
+    fn square(x: f32, y: f32, size: f32) -> Vec<Vec2> {
+        polygon(&[(x, y), (x + size, y), (x + size, y + size), (x, y + size)])
+    }
+
+    #[test]
+    fn concave() {
+        let l = polygon(&[
+            (0.0, 0.0),
+            (2.0, 0.0),
+            (2.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 2.0),
+            (0.0, 2.0),
+        ]);
+        assert_triangulates(&l, &[], 4, 3.0);
+    }
+
+    #[test]
+    fn either_winding() {
+        let mut l = polygon(&[
+            (0.0, 0.0),
+            (2.0, 0.0),
+            (2.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 2.0),
+            (0.0, 2.0),
+        ]);
+        l.reverse();
+        assert_triangulates(&l, &[], 4, 3.0);
+
+        // Holes may wind either way too, whatever the outline does.
+        let hole = square(1.0, 1.0, 2.0);
+        let mut reversed = hole.clone();
+        reversed.reverse();
+        assert_triangulates(&square(0.0, 0.0, 4.0), &[hole], 8, 12.0);
+        assert_triangulates(&square(0.0, 0.0, 4.0), &[reversed], 8, 12.0);
+    }
+
+    #[test]
+    fn one_hole() {
+        assert_triangulates(&square(0.0, 0.0, 4.0), &[square(1.0, 1.0, 2.0)], 8, 12.0);
+    }
+
+    #[test]
+    fn two_holes() {
+        let outer = polygon(&[(0.0, 0.0), (6.0, 0.0), (6.0, 3.0), (0.0, 3.0)]);
+        let holes = [square(1.0, 1.0, 1.0), square(4.0, 1.0, 1.0)];
+        // One bridge lines up with a hole's edge, so its vertex is dropped
+        // rather than clipped as a triangle with no area.
+        assert_triangulates(&outer, &holes, 13, 16.0);
+    }
+
+    #[test]
+    fn collinear_vertices() {
+        let square = polygon(&[
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (2.0, 0.0),
+            (2.0, 1.0),
+            (2.0, 2.0),
+            (1.0, 2.0),
+            (0.0, 2.0),
+            (0.0, 1.0),
+        ]);
+        assert_triangulates(&square, &[], 6, 4.0);
+
+        let line = polygon(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (3.0, 0.0)]);
+        assert_triangulates(&line, &[], 0, 0.0);
+    }
+
+    #[test]
+    fn no_ears_left() {
+        // A spike doubling back along y = 2 into an outline that crosses
+        // itself: no vertex is an ear, so the fallback clips one anyway. The
+        // crossing has no single area to cover; this checks it terminates.
+        let tangle = polygon(&[(0.0, 2.0), (2.0, 2.0), (1.0, 2.0), (0.0, 3.0), (1.0, 3.0)]);
+        let indices = triangulate(&tangle, &[]);
+        assert_eq!(indices.len(), 9);
+        assert!(indices.iter().all(|&i| (i as usize) < tangle.len()));
+    }
+}
@@ -0,0 +1,133 @@
+//! Turtle graphics: a `Turtle` walks the canvas leaving a line behind it,
+//! steered by turning left and right rather than by coordinates. Its lines are
+//! ordinary `line()`s, so they go through the sketch matrix and `layer()`.
+
+use bevy::prelude::*;
+
+use super::{ProcessingCommand, matrix, send, style};
+
+/// Where a turtle is, which way it faces and how its pen is set.
+#[derive(Clone, Copy, Debug)]
+struct Pose {
+    position: Vec2,
+    /// Radians clockwise from the positive x axis, as `rotate()` turns.
+    heading: f32,
+    pen_down: bool,
+    /// `None` draws with the sketch's `stroke()`.
+    color: Option<Color>,
+    /// `None` draws with the sketch's `stroke_weight()`.
+    width: Option<f32>,
+}
+
+/// A pen that moves by `forward()` and `back()` and turns by `left()` and
+/// `right()`, drawing a line wherever it goes while the pen is down. Angles
+/// are in degrees, as turtles are usually taught.
+#[derive(Clone, Debug)]
+pub struct Turtle {
+    pose: Pose,
+    /// Saved by `push()` for `pop()`.
+    stack: Vec<Pose>,
+}
+
+impl Turtle {
+    /// A turtle at `(x, y)` facing up the canvas, with its pen down and
+    /// drawing in the current `stroke()` and `stroke_weight()`.
+    pub fn new(x: f32, y: f32) -> Self {
+        Self {
+            pose: Pose {
+                position: Vec2::new(x, y),
+                heading: -std::f32::consts::FRAC_PI_2,
+                pen_down: true,
+                color: None,
+                width: None,
+            },
+            stack: Vec::new(),
+        }
+    }
+
+    /// Moves `distance` pixels the way the turtle faces, drawing a line if the pen is down.
+    pub fn forward(&mut self, distance: f32) {
+        let from = self.pose.position;
+        let to = from + Vec2::from_angle(self.pose.heading) * distance;
+        if self.pose.pen_down {
+            let mut style = style();
+            style.stroke = self.pose.color.or(style.stroke);
+            style.stroke_weight = self.pose.width.unwrap_or(style.stroke_weight);
+            send(ProcessingCommand::Line {
+                x1: from.x,
+                y1: from.y,
+                x2: to.x,
+                y2: to.y,
+                style,
+                transform: matrix(),
+            });
+        }
+        self.pose.position = to;
+    }
+
+    /// Moves `distance` pixels backwards, still facing the same way.
+    pub fn back(&mut self, distance: f32) {
+        self.forward(-distance);
+    }
+
+    /// Turns anticlockwise on the canvas by `degrees`.
+    pub fn left(&mut self, degrees: f32) {
+        self.pose.heading -= degrees.to_radians();
+    }
+
+    /// Turns clockwise on the canvas by `degrees`.
+    pub fn right(&mut self, degrees: f32) {
+        self.pose.heading += degrees.to_radians();
+    }
+
+    /// Stops drawing while moving.
+    pub fn pen_up(&mut self) {
+        self.pose.pen_down = false;
+    }
+
+    /// Draws while moving again after `pen_up()`.
+    pub fn pen_down(&mut self) {
+        self.pose.pen_down = true;
+    }
+
+    pub fn is_pen_down(&self) -> bool {
+        self.pose.pen_down
+    }
+
+    /// Draws in `color` instead of the sketch's `stroke()`.
+    pub fn pen_color(&mut self, color: Color) {
+        self.pose.color = Some(color);
+    }
+
+    /// Draws `width` pixels wide instead of the sketch's `stroke_weight()`.
+    pub fn pen_width(&mut self, width: f32) {
+        self.pose.width = Some(width);
+    }
+
+    /// Saves the position, heading and pen for `pop()` to return to.
+    pub fn push(&mut self) {
+        self.stack.push(self.pose);
+    }
+
+    /// Jumps back, without drawing, to where the last `push()` was, and
+    /// restores the heading and pen from then.
+    pub fn pop(&mut self) {
+        match self.stack.pop() {
+            Some(pose) => self.pose = pose,
+            None => warn!("Turtle::pop() called more times than Turtle::push()"),
+        }
+    }
+
+    pub fn position(&self) -> Vec2 {
+        self.pose.position
+    }
+
+    /// Degrees clockwise from facing right, so facing up is -90.
+    pub fn heading(&self) -> f32 {
+        self.pose.heading.to_degrees()
+    }
+
+    pub fn set_heading(&mut self, degrees: f32) {
+        self.pose.heading = degrees.to_radians();
+    }
+}